@@ -0,0 +1,62 @@
+//! What the benches that play one player against another have in common.
+
+use std::{env, fmt};
+
+use connect_four_mcts::{CFGameState, CFMove, GameState, Position};
+
+/// The arguments given after `--` on the command line.
+pub fn args() -> impl Iterator<Item = String> {
+    // cargo passes `--bench` along to benches without the default harness
+    env::args().skip(1).filter(|arg| !arg.starts_with("--"))
+}
+
+/// The results of the player under test over a number of games.
+#[derive(Debug, Default)]
+pub struct Score {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} wins, {} draws, {} losses",
+            self.wins, self.draws, self.losses
+        )
+    }
+}
+
+/// Plays `games` games, the player under test taking Red in even ones and Black in odd ones.
+/// `choose` picks the column to play in a position, given whether the player under test is to
+/// move.
+pub fn score(games: u32, mut choose: impl FnMut(&CFGameState, bool) -> usize) -> Score {
+    let mut score = Score::default();
+    for game in 0..games {
+        let tested = if game % 2 == 0 {
+            Position::Red
+        } else {
+            Position::Black
+        };
+        match play(|state| choose(state, state.next_player() == tested)) {
+            Position::Empty => score.draws += 1,
+            winner if winner == tested => score.wins += 1,
+            _ => score.losses += 1,
+        }
+    }
+    score
+}
+
+/// Plays one game and returns the winner, `Empty` for a draw.
+fn play(mut choose: impl FnMut(&CFGameState) -> usize) -> Position {
+    let mut state = CFGameState::default();
+    loop {
+        if let Some(result) = state.is_terminal_state() {
+            return result;
+        }
+        let color = state.next_player();
+        let col = choose(&state);
+        state = state.apply_move(CFMove { color, col });
+    }
+}
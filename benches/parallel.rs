@@ -0,0 +1,58 @@
+//! Measures what root parallelization buys: iterations per second for a growing number of
+//! threads, and the score of a parallel search against a single thread at the same time per move.
+//!
+//! Run with `cargo bench --bench parallel -- [games] [milliseconds per move] [threads]`, where
+//! the threads default to one per CPU core.
+
+mod common;
+
+use std::{thread, time::Duration};
+
+use connect_four_mcts::{search, Budget, CFGameState, CFMove, Search, SearchHandle, Snapshot};
+
+fn think(state: &CFGameState, threads: usize, time: Duration) -> Snapshot<CFMove> {
+    let searches = (0..threads).map(|_| Search::new(state.clone())).collect();
+    let handle = SearchHandle::spawn_parallel(searches, Budget::Time(time));
+    while !handle.is_finished() {
+        thread::sleep(Duration::from_millis(1));
+    }
+    handle.stop()
+}
+
+fn main() {
+    let mut args = common::args();
+    let games = args.next().map_or(20, |arg| arg.parse().expect("games"));
+    let time = Duration::from_millis(args.next().map_or(100, |arg| arg.parse().expect("time")));
+
+    let max_threads = args
+        .next()
+        .map_or_else(search::default_threads, |arg| arg.parse().expect("threads"));
+    let mut threads = 1;
+    let mut single = 0.0;
+    loop {
+        let snapshot = think(&CFGameState::default(), threads, Duration::from_secs(1));
+        let per_second = snapshot.iterations as f64;
+        if threads == 1 {
+            single = per_second;
+        }
+        println!(
+            "{threads:>3} threads: {per_second:>9.0} iterations/s, {:.2}x",
+            per_second / single
+        );
+
+        if threads == max_threads {
+            break;
+        }
+        threads = (threads * 2).min(max_threads);
+    }
+
+    let score = common::score(games, |state, parallel| {
+        let threads = if parallel { max_threads } else { 1 };
+        think(state, threads, time).best().unwrap().action.col
+    });
+
+    println!(
+        "{max_threads} threads vs 1 thread, {games} games at {}ms per move: {score}",
+        time.as_millis()
+    );
+}
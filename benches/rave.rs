@@ -0,0 +1,42 @@
+//! Plays a RAVE search against plain UCT at the same time per move, and compares their speed.
+//!
+//! Run with `cargo bench --bench rave -- [games] [milliseconds per move] [k]`, where `k` is the
+//! RAVE equivalence parameter passed to `Search::rave`.
+
+mod common;
+
+use std::time::Duration;
+
+use connect_four_mcts::{CFGameState, Search};
+
+fn search(state: &CFGameState, rave: Option<f64>) -> Search<CFGameState> {
+    let search = Search::new(state.clone());
+    match rave {
+        Some(k) => search.rave(k),
+        None => search,
+    }
+}
+
+fn main() {
+    let mut args = common::args();
+    let games = args.next().map_or(20, |arg| arg.parse().expect("games"));
+    let time = Duration::from_millis(args.next().map_or(100, |arg| arg.parse().expect("time")));
+    let k = args.next().map_or(1000.0, |arg| arg.parse().expect("k"));
+
+    for (name, rave) in [("uct", None), ("rave", Some(k))] {
+        let mut search = search(&CFGameState::default(), rave);
+        search.run_for(Duration::from_secs(1));
+        println!("{name:>5}: {:>9} iterations/s", search.iterations());
+    }
+
+    let score = common::score(games, |state, rave| {
+        let mut search = search(state, rave.then_some(k));
+        search.run_for(time);
+        search.best().unwrap().action.col
+    });
+
+    println!(
+        "rave (k = {k}) vs uct, {games} games at {}ms per move: {score}",
+        time.as_millis()
+    );
+}
@@ -0,0 +1,57 @@
+//! Compares the heuristic playout policy against purely random playouts.
+//!
+//! Run with `cargo bench --bench rollouts -- [games] [iterations]`. Both sides get the same
+//! number of iterations per move and swap colors every game, so the score measures how much the
+//! better playouts are worth; the speed of each policy is reported as well.
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use connect_four_mcts::{search::RandomRollout, CFGameState, HeuristicRollout, Rollout, Search};
+
+fn search<R: Rollout<CFGameState> + 'static>(
+    state: &CFGameState,
+    rollout: R,
+    iterations: u32,
+) -> usize {
+    let mut search = Search::new(state.clone()).rollout(rollout);
+    search.run_iterations(iterations);
+    search.best().unwrap().action.col
+}
+
+/// Searches the opening position for `duration` and returns iterations per second.
+fn speed<R: Rollout<CFGameState> + 'static>(rollout: R, duration: Duration) -> f64 {
+    let mut search = Search::new(CFGameState::default()).rollout(rollout);
+    let start = Instant::now();
+    search.run_for(duration);
+    search.iterations() as f64 / start.elapsed().as_secs_f64()
+}
+
+fn main() {
+    let mut args = common::args();
+    let games = args.next().map_or(20, |arg| arg.parse().expect("games"));
+    let iterations = args
+        .next()
+        .map_or(2000, |arg| arg.parse().expect("iterations"));
+
+    let duration = Duration::from_secs(1);
+    println!(
+        "random:    {:>9.0} iterations/s",
+        speed(RandomRollout, duration)
+    );
+    println!(
+        "heuristic: {:>9.0} iterations/s",
+        speed(HeuristicRollout::default(), duration)
+    );
+
+    let score = common::score(games, |state, heuristic| {
+        if heuristic {
+            search(state, HeuristicRollout::default(), iterations)
+        } else {
+            search(state, RandomRollout, iterations)
+        }
+    });
+
+    println!("heuristic vs random, {games} games at {iterations} iterations per move: {score}");
+}
@@ -0,0 +1,9 @@
+#!/bin/sh
+# Builds and tests every supported combination of cargo features.
+set -e
+
+for features in "" "net" "serde" "server" "net,server" "tui" "tui,server"; do
+    echo "== features: [$features]"
+    cargo clippy --no-default-features --features "$features" --all-targets -- -D warnings
+    cargo test --no-default-features --features "$features"
+done
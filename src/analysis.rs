@@ -0,0 +1,250 @@
+//! Analysis mode, where the engine keeps searching whatever position is on the board while
+//! moves are played for either side.
+
+use ratatui::{style::Stylize, text::Line};
+
+use connect_four_mcts::{
+    connect_four::{CFGameState, CFMove},
+    search::{Budget, Outcome, SearchHandle, Snapshot},
+    GameState,
+};
+
+use crate::{disc_style, player_name, pv_text, watch::AiConfig};
+
+pub struct Analysis {
+    ai: AiConfig,
+    /// The position being searched.
+    game: Option<CFGameState>,
+    search: Option<SearchHandle<CFGameState>>,
+}
+
+fn same_position(a: &CFGameState, b: &CFGameState) -> bool {
+    a.next_player() == b.next_player() && a.rows() == b.rows()
+}
+
+impl Analysis {
+    pub fn new(ai: AiConfig) -> Self {
+        Self {
+            ai,
+            game: None,
+            search: None,
+        }
+    }
+
+    /// Restarts the search whenever the position has changed since the last call. After a
+    /// single move the trees are kept, so the visits already spent on that move are not lost.
+    /// The search goes on until the position changes, its trees bounded by the node limit of
+    /// [`AiConfig::searches`].
+    pub fn poll(&mut self, game: &CFGameState) {
+        if self
+            .game
+            .as_ref()
+            .is_some_and(|searched| same_position(searched, game))
+        {
+            return;
+        }
+
+        let mut searches = Vec::new();
+        if let (Some(old), Some(handle)) = (self.game.take(), self.search.take()) {
+            let played = old
+                .all_moves()
+                .into_iter()
+                .find(|&action| same_position(&old.apply_move(action), game));
+            let mut previous = handle.into_searches();
+            if let Some(action) = played {
+                for search in &mut previous {
+                    search.advance(action);
+                }
+                searches = previous;
+            }
+        }
+
+        self.game = Some(game.clone());
+        if game.is_terminal_state().is_some() {
+            return;
+        }
+        if searches.is_empty() {
+            searches = self.ai.searches(game);
+        }
+        self.search = Some(SearchHandle::spawn_parallel(searches, Budget::Infinite));
+    }
+
+    /// The statistics of the search of `game`, once it has started.
+    pub fn snapshot(&self, game: &CFGameState) -> Option<Snapshot<CFMove>> {
+        let searched = self.game.as_ref()?;
+        if !same_position(searched, game) {
+            return None;
+        }
+        self.search.as_ref().map(SearchHandle::snapshot)
+    }
+
+    /// The engine's move in `game`.
+    pub fn best(&self, game: &CFGameState) -> Option<CFMove> {
+        let snapshot = self.snapshot(game)?;
+        snapshot.select(self.ai.selection).map(|best| best.action)
+    }
+
+    /// The line of play expected from `game`, as far as the search has gone.
+    pub fn pv(&self, game: &CFGameState) -> Vec<CFMove> {
+        self.snapshot(game).map_or_else(Vec::new, |s| s.pv)
+    }
+
+    /// The lines of the analysis panel.
+    pub fn lines(&self, game: &CFGameState) -> Vec<Line<'static>> {
+        let mut lines = Vec::new();
+        let player = game.next_player();
+
+        if game.is_terminal_state().is_some() {
+            lines.push(Line::from("Game over").gray().bold());
+            lines.push(Line::from("  take back a move to keep analysing"));
+            return lines;
+        }
+
+        let threads = if self.ai.threads == 1 {
+            "thread"
+        } else {
+            "threads"
+        };
+        let header = format!(
+            "{} to move  {} {threads}  searching",
+            player_name(player),
+            self.ai.threads
+        );
+        lines.push(Line::from(header).fg(disc_style(player).1).bold());
+
+        let Some(snapshot) = self.snapshot(game) else {
+            lines.push(Line::from("  starting"));
+            return lines;
+        };
+
+        match snapshot.select(self.ai.selection) {
+            Some(best) => lines.push(Line::from(format!(
+                "  {} iterations, best {}, {:.1}% wins",
+                snapshot.iterations,
+                best.action.col + 1,
+                best.win_rate * 100.0
+            ))),
+            None => lines.push(Line::from("  starting")),
+        }
+
+        // the solver's verdict on the position, once every move or a winning one is proven
+        let all_proven = |outcome: Option<Outcome>| {
+            !snapshot.stats.is_empty()
+                && snapshot
+                    .stats
+                    .iter()
+                    .all(|s| outcome.map_or(s.proven.is_some(), |o| s.proven == Some(o)))
+        };
+        let verdict = if snapshot.forced_win().is_some() {
+            Some(format!("{} wins by force", player_name(player)))
+        } else if all_proven(Some(Outcome::Loss)) {
+            Some(format!("{} wins by force", player_name(player.opponent())))
+        } else if all_proven(None) {
+            Some("drawn with best play".to_string())
+        } else {
+            None
+        };
+        if let Some(verdict) = verdict {
+            lines.push(Line::from(format!("  {verdict}")).bold());
+        }
+
+        if !snapshot.pv.is_empty() {
+            lines.push(Line::from(format!("  pv {}", pv_text(&snapshot.pv))));
+        }
+
+        let total = snapshot.stats.iter().map(|s| s.visits).sum::<u64>().max(1);
+        let mut stats = snapshot.stats.clone();
+        stats.sort_by_key(|s| s.action.col);
+        for s in stats {
+            let proven = match s.proven {
+                Some(Outcome::Win) => "  wins",
+                Some(Outcome::Loss) => "  loses",
+                Some(Outcome::Draw) => "  draws",
+                None => "",
+            };
+            lines.push(
+                Line::from(format!(
+                    "  column {}  {:>5.1}% wins  {:>3}% visits{proven}",
+                    s.action.col + 1,
+                    s.win_rate * 100.0,
+                    s.visits * 100 / total
+                ))
+                .gray(),
+            );
+        }
+
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use connect_four_mcts::{
+        notation,
+        search::{MoveSelection, SearchConfig},
+    };
+
+    use super::*;
+
+    fn analysis() -> Analysis {
+        Analysis::new(AiConfig {
+            budget: Budget::Infinite,
+            selection: MoveSelection::MostVisits,
+            threads: 1,
+            search: SearchConfig {
+                seed: Some(1),
+                ..SearchConfig::default()
+            },
+        })
+    }
+
+    #[test]
+    fn transpositions() {
+        let a = notation::parse("1234").unwrap();
+        assert!(same_position(&a, &notation::parse("3214").unwrap()));
+        assert!(!same_position(&a, &notation::parse("1243").unwrap()));
+        assert!(!same_position(&a, &notation::parse("123").unwrap()));
+    }
+
+    #[test]
+    fn keeps_trees() {
+        let mut analysis = analysis();
+        let start = CFGameState::default();
+        analysis.poll(&start);
+        let best = loop {
+            let snapshot = analysis.snapshot(&start).unwrap();
+            if snapshot.iterations >= 2000 {
+                break snapshot.best().unwrap();
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+        assert!(analysis.best(&start).is_some());
+
+        // the tree below the move played is searched further
+        let game = start.apply_move(best.action);
+        analysis.poll(&game);
+        assert!(analysis.snapshot(&start).is_none());
+        let visits = analysis
+            .snapshot(&game)
+            .unwrap()
+            .stats
+            .iter()
+            .map(|s| s.visits)
+            .sum::<u64>();
+        assert!(visits + 1 >= best.visits);
+
+        // polling the same position again keeps the search going
+        let iterations = analysis.snapshot(&game).unwrap().iterations;
+        analysis.poll(&game);
+        assert!(analysis.snapshot(&game).unwrap().iterations >= iterations);
+
+        analysis.poll(&notation::parse("1212121").unwrap());
+        assert!(analysis.search.is_none());
+        assert_eq!(
+            analysis.lines(&notation::parse("1212121").unwrap()).len(),
+            2
+        );
+    }
+}
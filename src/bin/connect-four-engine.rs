@@ -0,0 +1,7 @@
+//! Speaks the engine protocol on stdin/stdout, without the terminal UI.
+
+use std::io;
+
+fn main() -> io::Result<()> {
+    connect_four_mcts::engine::run(io::stdin().lock(), io::stdout())
+}
@@ -0,0 +1,229 @@
+//! Settings for the AI, given on the command line or in a config file.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use clap::Args;
+use connect_four_mcts::search::MoveSelection;
+
+/// The AI settings of the modes with an AI player or hints. A config file sets them with `key = value` lines, using
+/// the names of the command line options, which take precedence:
+///
+/// ```text
+/// # slower, but the same moves every time
+/// iterations = 20000
+/// seed = 7
+/// exploration = 1.0
+/// selection = robust-max
+/// draw-value = 0.5
+/// ```
+#[derive(Args, Debug, Default, Clone)]
+pub struct Settings {
+    /// Threads for each AI search, one per CPU core by default, or one with --seed
+    #[arg(long)]
+    pub threads: Option<usize>,
+    /// Iterations per AI move, in place of a thinking time
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub iterations: Option<u32>,
+    /// Seeds the AI, which then plays the same moves on every run of the same game with the same
+    /// --threads (needs --iterations)
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// UCT exploration constant, higher values try more moves [default: 1.414]
+    #[arg(long, value_parser = parse_exploration)]
+    pub exploration: Option<f64>,
+    /// How the AI picks its move once it has searched: most-visits, highest-value or robust-max
+    /// [default: most-visits]
+    #[arg(long)]
+    pub selection: Option<MoveSelection>,
+    /// What a draw is worth to the AI, from 0 like a loss to 1 like a win [default: 0]
+    #[arg(long, value_parser = parse_draw_value)]
+    pub draw_value: Option<f64>,
+    /// Blends all-moves-as-first statistics into the search (RAVE), with this many visits at
+    /// which both count about as much, for example 1000
+    #[arg(long, value_parser = parse_rave)]
+    pub rave: Option<f64>,
+}
+
+/// The AI options of a mode: its settings, and a config file for those not given.
+#[derive(Args, Debug, Default, Clone)]
+pub struct AiArgs {
+    #[command(flatten)]
+    pub settings: Settings,
+    /// Reads AI settings from a file of `key = value` lines, see the README
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+}
+
+impl AiArgs {
+    /// The settings from the command line, with the missing ones from the config file.
+    pub fn settings(self) -> Result<Settings, String> {
+        match &self.config {
+            Some(path) => Ok(self.settings.or(Settings::load(path)?)),
+            None => Ok(self.settings),
+        }
+    }
+}
+
+impl Settings {
+    /// Reads a config file.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{}: {err}", path.display()))?;
+        Self::parse(&text).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut settings = Settings::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected 'key = value'", i + 1))?;
+            let (key, value) = (key.trim(), value.trim());
+            let invalid = || format!("line {}: invalid {key} '{value}'", i + 1);
+            match key {
+                "threads" => settings.threads = Some(parse(value).ok_or_else(invalid)?),
+                "iterations" => {
+                    let iterations = parse(value).filter(|&n: &u32| n > 0);
+                    settings.iterations = Some(iterations.ok_or_else(invalid)?);
+                }
+                "seed" => settings.seed = Some(parse(value).ok_or_else(invalid)?),
+                "exploration" => {
+                    settings.exploration = Some(parse_exploration(value).map_err(|_| invalid())?);
+                }
+                "selection" => settings.selection = Some(parse(value).ok_or_else(invalid)?),
+                "draw-value" => {
+                    settings.draw_value = Some(parse_draw_value(value).map_err(|_| invalid())?);
+                }
+                "rave" => settings.rave = Some(parse_rave(value).map_err(|_| invalid())?),
+                _ => return Err(format!("line {}: unknown setting '{key}'", i + 1)),
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Takes the settings missing here from `other`.
+    pub fn or(self, other: Settings) -> Self {
+        Self {
+            threads: self.threads.or(other.threads),
+            iterations: self.iterations.or(other.iterations),
+            seed: self.seed.or(other.seed),
+            exploration: self.exploration.or(other.exploration),
+            selection: self.selection.or(other.selection),
+            draw_value: self.draw_value.or(other.draw_value),
+            rave: self.rave.or(other.rave),
+        }
+    }
+}
+
+fn parse<T: FromStr>(value: &str) -> Option<T> {
+    value.parse().ok()
+}
+
+/// Parses a UCT exploration constant, which can be any finite number from 0.
+pub fn parse_exploration(value: &str) -> Result<f64, String> {
+    parse(value)
+        .filter(|c: &f64| c.is_finite() && *c >= 0.0)
+        .ok_or_else(|| format!("'{value}' is not a number from 0"))
+}
+
+/// Parses the value of a draw, from 0 to 1.
+fn parse_draw_value(value: &str) -> Result<f64, String> {
+    parse(value)
+        .filter(|v: &f64| (0.0..=1.0).contains(v))
+        .ok_or_else(|| format!("'{value}' is not a number from 0 to 1"))
+}
+
+/// Parses the RAVE equivalence parameter, a finite number above 0.
+fn parse_rave(value: &str) -> Result<f64, String> {
+    parse(value)
+        .filter(|k: &f64| k.is_finite() && *k > 0.0)
+        .ok_or_else(|| format!("'{value}' is not a number above 0"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_files() {
+        let settings = Settings::parse(
+            "# a comment\n\
+             \n\
+             iterations = 20000\n\
+             seed=7 # the same moves every time\n\
+             exploration = 1.0\n\
+             selection = robust-max\n\
+             draw-value = 0.5\n\
+             rave = 1000\n",
+        )
+        .unwrap();
+        assert_eq!(settings.threads, None);
+        assert_eq!(settings.iterations, Some(20000));
+        assert_eq!(settings.seed, Some(7));
+        assert_eq!(settings.exploration, Some(1.0));
+        assert_eq!(settings.selection, Some(MoveSelection::RobustMax));
+        assert_eq!(settings.draw_value, Some(0.5));
+        assert_eq!(settings.rave, Some(1000.0));
+    }
+
+    #[test]
+    fn rejects_bad_lines() {
+        let error = |text| Settings::parse(text).unwrap_err();
+        assert_eq!(
+            error("seed = 1\nhash = 16"),
+            "line 2: unknown setting 'hash'"
+        );
+        assert_eq!(error("iterations"), "line 1: expected 'key = value'");
+        assert_eq!(error("iterations = 0"), "line 1: invalid iterations '0'");
+        assert_eq!(error("threads = many"), "line 1: invalid threads 'many'");
+        assert_eq!(
+            error("selection = best"),
+            "line 1: invalid selection 'best'"
+        );
+        assert_eq!(
+            error("exploration = -1"),
+            "line 1: invalid exploration '-1'"
+        );
+        assert_eq!(
+            error("exploration = inf"),
+            "line 1: invalid exploration 'inf'"
+        );
+        assert_eq!(
+            error("exploration = NaN"),
+            "line 1: invalid exploration 'NaN'"
+        );
+        assert_eq!(
+            error("draw-value = 1.5"),
+            "line 1: invalid draw-value '1.5'"
+        );
+        assert_eq!(
+            error("draw-value = NaN"),
+            "line 1: invalid draw-value 'NaN'"
+        );
+        assert_eq!(error("rave = 0"), "line 1: invalid rave '0'");
+        assert_eq!(error("rave = -1000"), "line 1: invalid rave '-1000'");
+    }
+
+    #[test]
+    fn command_line_first() {
+        let cli = Settings {
+            iterations: Some(100),
+            exploration: Some(0.5),
+            ..Settings::default()
+        };
+        let file = Settings::parse("iterations = 20000\nexploration = 1.0\nseed = 7").unwrap();
+        let settings = cli.or(file);
+        assert_eq!(settings.iterations, Some(100));
+        assert_eq!(settings.exploration, Some(0.5));
+        assert_eq!(settings.seed, Some(7));
+        assert_eq!(settings.threads, None);
+    }
+}
@@ -1,66 +1,253 @@
+//! The rules of Connect Four on the standard board of 7 columns and 6 rows.
+
+use std::fmt;
+
 use yamcts::GameState;
 
+/// The contents of a cell, which also names the players.
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum Position {
+    /// The first player.
     Red,
+    /// The second player.
     Black,
+    /// No disc, or a tie as the result of a finished game.
     Empty,
 }
 
+impl Position {
+    /// The other player, `Empty` stays `Empty`.
+    pub fn opponent(self) -> Position {
+        match self {
+            Position::Red => Position::Black,
+            Position::Black => Position::Red,
+            Position::Empty => Position::Empty,
+        }
+    }
+}
+
+/// Dropping a disc into a column.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct CFMove {
-    pub color: Position, // Red or Black
+    /// The player making the move, Red or Black.
+    pub color: Position,
+    /// The column counted from 0 on the left.
     pub col: usize,
 }
 
+/// Why cells do not make up a position that can be reached by playing, see
+/// [`CFGameState::from_cells`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoardError {
+    /// The player to move is `Empty`.
+    NoPlayerToMove,
+    /// A disc with an empty cell below it, in the column counted from 0.
+    FloatingDisc(usize),
+    /// Red, who moves first, has neither as many discs as Black nor one more.
+    NotAlternated,
+    /// Red is not to move with as many discs as Black, or Black with one fewer than Red.
+    WrongPlayerToMove,
+    /// Both players have four in a row.
+    TwoWinners,
+    /// The player to move has four in a row, which only the player who moved last can have.
+    WinnerToMove,
+}
+
+impl fmt::Display for BoardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardError::NoPlayerToMove => write!(f, "the player to move must be red or black"),
+            BoardError::FloatingDisc(col) => write!(f, "floating disc in column {}", col + 1),
+            BoardError::NotAlternated => {
+                write!(f, "red must have as many discs as black or one more")
+            }
+            BoardError::WrongPlayerToMove => write!(
+                f,
+                "red moves when both players have as many discs and black when red has one more"
+            ),
+            BoardError::TwoWinners => write!(f, "only one player can have four in a row"),
+            BoardError::WinnerToMove => {
+                write!(f, "the player with four in a row must have moved last")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BoardError {}
+
+/// A position in a game of Connect Four.
 #[derive(Debug, Clone)]
 pub struct CFGameState {
     // 7 columns, 6 rows
     board: [Position; 7 * 6],
     prev_player: Position, // Red or Black
     next_player: Position, // Red or Black
+    // index of the last disc dropped, if it is known
+    last_disc: Option<usize>,
+    // the result of is_terminal_state, kept up to date by apply_move
+    result: Option<Position>,
 }
 
 impl CFGameState {
+    /// An empty board. See also [`CFGameState::default`], where Red moves first.
     pub fn new(prev_player: Position, next_player: Position) -> Self {
         Self {
             board: [Position::Empty; 7 * 6],
             prev_player,
             next_player,
+            last_disc: None,
+            result: None,
         }
     }
 
-    // col=0, row=0 is top-left
+    /// Builds a position from its cells, row by row from the top-left. The discs must rest on
+    /// the bottom or on other discs, the players must have taken turns with Red first, and only
+    /// the player who moved last may have four in a row. That does not make every such position
+    /// reachable, since the order of the discs in a column and any win before the last move go
+    /// unchecked.
+    pub fn from_cells(board: [Position; 7 * 6], next_player: Position) -> Result<Self, BoardError> {
+        if next_player == Position::Empty {
+            return Err(BoardError::NoPlayerToMove);
+        }
+
+        for row in 0..5 {
+            for col in 0..7 {
+                if board[row * 7 + col] != Position::Empty
+                    && board[(row + 1) * 7 + col] == Position::Empty
+                {
+                    return Err(BoardError::FloatingDisc(col));
+                }
+            }
+        }
+
+        let count = |p| board.iter().filter(|&&c| c == p).count();
+        let (red, black) = (count(Position::Red), count(Position::Black));
+        let to_move = match red.checked_sub(black) {
+            Some(0) => Position::Red,
+            Some(1) => Position::Black,
+            _ => return Err(BoardError::NotAlternated),
+        };
+        if next_player != to_move {
+            return Err(BoardError::WrongPlayerToMove);
+        }
+
+        let state = Self::from_board(board, next_player);
+        match (
+            state.has_four(next_player),
+            state.has_four(next_player.opponent()),
+        ) {
+            (true, true) => Err(BoardError::TwoWinners),
+            (true, false) => Err(BoardError::WinnerToMove),
+            _ => Ok(state),
+        }
+    }
+
+    /// Whether `player` has four in a row anywhere on the board.
+    fn has_four(&self, player: Position) -> bool {
+        let lines = [(1, 0), (0, 1), (1, 1), (1, -1)];
+        (0..7).any(|col| {
+            (0..6).any(|row| {
+                lines.iter().any(|&(dc, dr)| {
+                    (0..4).all(|i| {
+                        let (c, r) = (col as isize + dc * i, row as isize + dr * i);
+                        (0..7).contains(&c)
+                            && (0..6).contains(&r)
+                            && self.pos(c as usize, r as usize) == player
+                    })
+                })
+            })
+        })
+    }
+
+    /// Builds a position from its cells without checking them.
+    fn from_board(board: [Position; 7 * 6], next_player: Position) -> Self {
+        let mut state = Self {
+            board,
+            prev_player: next_player.opponent(),
+            next_player,
+            last_disc: None,
+            result: None,
+        };
+        state.result = state.scan_result();
+        state
+    }
+
+    /// The player who will make the next move.
+    pub fn next_player(&self) -> Position {
+        self.next_player
+    }
+
+    /// Returns true if a disc can still be dropped into `col`.
+    pub fn can_play(&self, col: usize) -> bool {
+        col < 7 && self.board[col] == Position::Empty
+    }
+
+    /// The contents of a cell, where col=0, row=0 is top-left.
     pub fn pos(&self, col: usize, row: usize) -> Position {
         debug_assert!(col <= 7 && row <= 6);
         self.board[row * 7 + col]
     }
 
-    fn same_vals(&self, pos: [(usize, usize); 4], val: Position) -> bool {
-        pos.iter().all(|&(col, row)| self.pos(col, row) == val)
+    /// The board as its rows from top to bottom, with `R` for Red, `B` for Black and `.` for
+    /// empty cells.
+    pub fn rows(&self) -> [String; 6] {
+        std::array::from_fn(|row| {
+            (0..7)
+                .map(|col| match self.pos(col, row) {
+                    Position::Red => 'R',
+                    Position::Black => 'B',
+                    Position::Empty => '.',
+                })
+                .collect()
+        })
     }
-}
 
-impl GameState for CFGameState {
-    type Move = CFMove;
-    type UserData = Position;
+    /// The cell of the last disc dropped as `(col, row)`, if the position was reached by playing
+    /// a move.
+    pub fn last_disc(&self) -> Option<(usize, usize)> {
+        self.last_disc.map(|i| (i % 7, i / 7))
+    }
 
-    fn all_moves(&self) -> Vec<Self::Move> {
-        (0..7)
-            .filter_map(|i| {
-                if self.board[i] == Position::Empty {
-                    Some(CFMove {
-                        color: self.next_player,
-                        col: i,
-                    })
-                } else {
-                    None
-                }
-            })
-            .collect()
+    /// The row a disc dropped into `col` lands on, or `None` if the column is full.
+    pub fn landing_row(&self, col: usize) -> Option<usize> {
+        (0..6)
+            .rev()
+            .find(|&row| self.pos(col, row) == Position::Empty)
     }
 
-    fn is_terminal_state(&self) -> Option<Self::UserData> {
+    /// Returns true if a disc of `color` at `col`, `row` would complete four in a row, whatever
+    /// the cell currently holds.
+    pub fn completes_four(&self, col: usize, row: usize, color: Position) -> bool {
+        let run = |dc: isize, dr: isize| {
+            (1..4)
+                .take_while(|&i| {
+                    let c = col as isize + dc * i;
+                    let r = row as isize + dr * i;
+                    (0..7).contains(&c)
+                        && (0..6).contains(&r)
+                        && self.pos(c as usize, r as usize) == color
+                })
+                .count()
+        };
+
+        [(1, 0), (0, 1), (1, 1), (1, -1)]
+            .iter()
+            .any(|&(dc, dr)| 1 + run(dc, dr) + run(-dc, -dr) >= 4)
+    }
+
+    fn same_vals(&self, pos: [(usize, usize); 4], val: Position) -> bool {
+        pos.iter().all(|&(col, row)| self.pos(col, row) == val)
+    }
+
+    /// Looks for four in a row anywhere on the board, for positions that were not reached by
+    /// [`GameState::apply_move`].
+    #[allow(clippy::identity_op, clippy::eq_op)]
+    fn scan_result(&self) -> Option<Position> {
         use Position::*;
 
         macro_rules! build_same {
@@ -150,6 +337,37 @@ impl GameState for CFGameState {
 
         None
     }
+}
+
+impl Default for CFGameState {
+    /// The empty board with Red to move.
+    fn default() -> Self {
+        Self::new(Position::Black, Position::Red)
+    }
+}
+
+impl GameState for CFGameState {
+    type Move = CFMove;
+    type UserData = Position;
+
+    fn all_moves(&self) -> Vec<Self::Move> {
+        (0..7)
+            .filter_map(|i| {
+                if self.board[i] == Position::Empty {
+                    Some(CFMove {
+                        color: self.next_player,
+                        col: i,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn is_terminal_state(&self) -> Option<Self::UserData> {
+        self.result
+    }
 
     fn apply_move(&self, action: Self::Move) -> Self {
         use Position::*;
@@ -168,6 +386,14 @@ impl GameState for CFGameState {
         }
 
         new_state.board[row * 7 + action.col] = action.color;
+        new_state.last_disc = Some(row * 7 + action.col);
+        new_state.result = if new_state.completes_four(action.col, row, action.color) {
+            Some(action.color)
+        } else if row == 0 && (0..7).all(|col| new_state.pos(col, 0) != Empty) {
+            Some(Empty)
+        } else {
+            None
+        };
         new_state.prev_player = action.color;
         new_state.next_player = match action.color {
             Red => Black,
@@ -208,37 +434,137 @@ mod tests {
             };
         }
 
-        assert_eq!(state.is_terminal_state(), None);
+        assert_eq!(state.scan_result(), None);
 
         set_pos!(1, 5, 2, 5, 3, 5, 4, 5, Black);
-        assert_eq!(state.is_terminal_state(), Some(Black));
+        assert_eq!(state.scan_result(), Some(Black));
 
         clear_pos!();
-        assert_eq!(state.is_terminal_state(), None);
+        assert_eq!(state.scan_result(), None);
 
         set_pos!(2, 5, 3, 5, 4, 5, 5, 5, Red);
-        assert_eq!(state.is_terminal_state(), Some(Red));
+        assert_eq!(state.scan_result(), Some(Red));
         clear_pos!();
 
         set_pos!(1, 0, 1, 1, 1, 2, 1, 3, Red);
-        assert_eq!(state.is_terminal_state(), Some(Red));
+        assert_eq!(state.scan_result(), Some(Red));
         clear_pos!();
 
         set_pos!(2, 2, 3, 3, 4, 4, 5, 5, Black);
-        assert_eq!(state.is_terminal_state(), Some(Black));
+        assert_eq!(state.scan_result(), Some(Black));
         clear_pos!();
 
         set_pos!(2, 2, 3, 3, 4, 4, 5, 5, Black);
         set_pos!(5, 5, 5, 4, 5, 3, 5, 2, Red);
-        assert_eq!(state.is_terminal_state(), Some(Red));
+        assert_eq!(state.scan_result(), Some(Red));
         clear_pos!();
 
         set_pos!(0, 3, 1, 2, 2, 1, 3, 0, Red);
-        assert_eq!(state.is_terminal_state(), Some(Red));
+        assert_eq!(state.scan_result(), Some(Red));
         clear_pos!();
 
         set_pos!(2, 4, 3, 3, 4, 2, 5, 1, Red);
-        assert_eq!(state.is_terminal_state(), Some(Red));
+        assert_eq!(state.scan_result(), Some(Red));
         clear_pos!();
     }
+
+    #[test]
+    fn from_cells() {
+        use Position::*;
+        let mut board = [Empty; 7 * 6];
+        assert!(CFGameState::from_cells(board, Red).is_ok());
+        assert_eq!(
+            CFGameState::from_cells(board, Empty).unwrap_err(),
+            BoardError::NoPlayerToMove
+        );
+
+        board[5 * 7 + 3] = Red;
+        let state = CFGameState::from_cells(board, Black).unwrap();
+        assert_eq!(state.pos(3, 5), Red);
+        assert_eq!(state.next_player(), Black);
+        assert_eq!(
+            CFGameState::from_cells(board, Red).unwrap_err(),
+            BoardError::WrongPlayerToMove
+        );
+
+        board[5 * 7] = Red;
+        assert_eq!(
+            CFGameState::from_cells(board, Black).unwrap_err(),
+            BoardError::NotAlternated
+        );
+
+        // Red moves first, so Black never has more discs
+        board[5 * 7] = Black;
+        board[5 * 7 + 3] = Empty;
+        assert_eq!(
+            CFGameState::from_cells(board, Red).unwrap_err(),
+            BoardError::NotAlternated
+        );
+        board[5 * 7 + 3] = Red;
+        assert!(CFGameState::from_cells(board, Red).is_ok());
+        assert_eq!(
+            CFGameState::from_cells(board, Black).unwrap_err(),
+            BoardError::WrongPlayerToMove
+        );
+
+        board[5 * 7] = Empty;
+        board[3 * 7 + 3] = Black;
+        assert_eq!(
+            CFGameState::from_cells(board, Red).unwrap_err(),
+            BoardError::FloatingDisc(3)
+        );
+
+        // a finished game keeps its result
+        for row in 2..6 {
+            board[row * 7 + 3] = Red;
+        }
+        board[5 * 7..5 * 7 + 3].copy_from_slice(&[Black; 3]);
+        let state = CFGameState::from_cells(board, Black).unwrap();
+        assert_eq!(state.is_terminal_state(), Some(Red));
+
+        // only the player who moved last can have won
+        let cells = |rows: [&str; 6]| -> [Position; 7 * 6] {
+            let cells = rows.concat();
+            std::array::from_fn(|i| match cells.as_bytes()[i] {
+                b'R' => Red,
+                b'B' => Black,
+                _ => Empty,
+            })
+        };
+        let empty = ".......";
+        let board = cells([empty, empty, "R......", "R......", "R......", "RBBB..B"]);
+        assert_eq!(
+            CFGameState::from_cells(board, Red).unwrap_err(),
+            BoardError::WinnerToMove
+        );
+        let board = cells([empty, empty, "RB.....", "RB.....", "RB.....", "RB....."]);
+        assert_eq!(
+            CFGameState::from_cells(board, Red).unwrap_err(),
+            BoardError::TwoWinners
+        );
+        let board = cells([empty, "R......", "RB.....", "RB.....", "RB.....", "RB....."]);
+        assert_eq!(
+            CFGameState::from_cells(board, Black).unwrap_err(),
+            BoardError::TwoWinners
+        );
+    }
+
+    #[test]
+    fn incremental_matches_full_scan() {
+        use rand::{rngs::SmallRng, seq::SliceRandom, SeedableRng};
+
+        let mut rng = SmallRng::seed_from_u64(34);
+        for _ in 0..500 {
+            let mut state = CFGameState::default();
+            while state.is_terminal_state().is_none() {
+                let action = *state.all_moves().choose(&mut rng).unwrap();
+                state = state.apply_move(action);
+
+                assert_eq!(state.is_terminal_state(), state.scan_result());
+                let (col, row) = state.last_disc().unwrap();
+                assert_eq!(col, action.col);
+                assert_eq!(state.pos(col, row), action.color);
+            }
+        }
+    }
 }
@@ -0,0 +1,64 @@
+//! Writing the AI's search trees to files, see [`connect_four_mcts::export`].
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use connect_four_mcts::{export, search::TreeNode, CFGameState, Search};
+
+const TREE_DEPTH: usize = 3;
+const TREE_MIN_VISITS: u64 = 10;
+
+/// Which part of the AI's trees to write, and where. Only the commands with an AI player to
+/// watch take these options.
+#[derive(Args, Debug, Clone)]
+pub struct TreeOptions {
+    /// Writes the tree of every AI move to this directory, as tree-<move>.dot and
+    /// tree-<move>.json
+    #[arg(long, value_name = "DIR")]
+    pub dump_trees: Option<PathBuf>,
+    /// Moves deep to write the trees, from the position the AI moved in
+    #[arg(long, default_value_t = TREE_DEPTH)]
+    pub tree_depth: usize,
+    /// Leaves out positions with fewer visits from the trees
+    #[arg(long, default_value_t = TREE_MIN_VISITS)]
+    pub tree_min_visits: u64,
+}
+
+impl Default for TreeOptions {
+    fn default() -> Self {
+        Self {
+            dump_trees: None,
+            tree_depth: TREE_DEPTH,
+            tree_min_visits: TREE_MIN_VISITS,
+        }
+    }
+}
+
+impl TreeOptions {
+    /// The tree of all threads searching one position together, cut down to the options.
+    pub fn tree(&self, searches: &[Search<CFGameState>]) -> Option<TreeNode<CFGameState>> {
+        let mut trees = searches.iter().map(|search| search.tree(self.tree_depth));
+        let mut tree = trees.next()?;
+        for other in trees {
+            tree.merge(other);
+        }
+        tree.prune(self.tree_min_visits);
+        Some(tree)
+    }
+
+    /// Writes the tree searched for move number `number` of the game to the dump directory, or
+    /// the current directory without one, and returns the names of the files.
+    pub fn write(&self, tree: &TreeNode<CFGameState>, number: usize) -> io::Result<[PathBuf; 2]> {
+        let dir = self.dump_trees.as_deref().unwrap_or(Path::new("."));
+        fs::create_dir_all(dir)?;
+
+        let dot = dir.join(format!("tree-{number}.dot"));
+        fs::write(&dot, export::dot(tree))?;
+        let json = dir.join(format!("tree-{number}.json"));
+        fs::write(&json, export::json(tree))?;
+        Ok([dot, json])
+    }
+}
@@ -0,0 +1,109 @@
+//! Setting up a position cell by cell, to play it or study it.
+
+use connect_four_mcts::connect_four::{BoardError, CFGameState, Position};
+
+pub struct Editor {
+    cells: [Position; 7 * 6],
+    /// The cell under the cursor as `(col, row)`, where row 0 is the top.
+    cursor: (usize, usize),
+}
+
+impl Editor {
+    /// Starts from `game`, with the cursor at the bottom of `col`.
+    pub fn new(game: &CFGameState, col: usize) -> Self {
+        Self {
+            cells: std::array::from_fn(|i| game.pos(i % 7, i / 7)),
+            cursor: (col, 5),
+        }
+    }
+
+    /// The contents of a cell, where col=0, row=0 is top-left.
+    pub fn pos(&self, col: usize, row: usize) -> Position {
+        self.cells[row * 7 + col]
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    /// Red moves first, so Black is to move when Red has more discs and Red otherwise.
+    pub fn next_player(&self) -> Position {
+        let count = |p| self.cells.iter().filter(|&&c| c == p).count();
+        if count(Position::Red) > count(Position::Black) {
+            Position::Black
+        } else {
+            Position::Red
+        }
+    }
+
+    /// Moves the cursor by `dc` columns and `dr` rows, stopping at the edges of the board.
+    pub fn move_cursor(&mut self, dc: isize, dr: isize) {
+        let (col, row) = self.cursor;
+        self.cursor = (
+            col.saturating_add_signed(dc).min(6),
+            row.saturating_add_signed(dr).min(5),
+        );
+    }
+
+    /// Puts a disc of `player`, or nothing for `Empty`, in the cell under the cursor.
+    pub fn set(&mut self, player: Position) {
+        let (col, row) = self.cursor;
+        self.cells[row * 7 + col] = player;
+    }
+
+    pub fn clear(&mut self) {
+        self.cells = [Position::Empty; 7 * 6];
+    }
+
+    /// The position set up, if it could have been reached by playing.
+    pub fn finish(&self) -> Result<CFGameState, BoardError> {
+        CFGameState::from_cells(self.cells, self.next_player())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use connect_four_mcts::{notation, GameState};
+
+    use super::*;
+
+    #[test]
+    fn cursor_stays_on_the_board() {
+        let mut editor = Editor::new(&CFGameState::default(), 3);
+        assert_eq!(editor.cursor(), (3, 5));
+        editor.move_cursor(1, 1);
+        assert_eq!(editor.cursor(), (4, 5));
+        editor.move_cursor(-9, -9);
+        assert_eq!(editor.cursor(), (0, 0));
+        editor.move_cursor(9, 2);
+        assert_eq!(editor.cursor(), (6, 2));
+    }
+
+    #[test]
+    fn sets_up_positions() {
+        let mut editor = Editor::new(&notation::parse("44").unwrap(), 0);
+        assert_eq!(editor.pos(3, 4), Position::Black);
+        assert_eq!(editor.next_player(), Position::Red);
+
+        editor.set(Position::Red);
+        assert_eq!(editor.next_player(), Position::Black);
+        let game = editor.finish().unwrap();
+        assert_eq!(game.pos(0, 5), Position::Red);
+        assert_eq!(game.next_player(), Position::Black);
+        assert!(game.is_terminal_state().is_none());
+
+        // a disc in the air
+        editor.move_cursor(1, -1);
+        editor.set(Position::Black);
+        assert_eq!(editor.finish().unwrap_err(), BoardError::FloatingDisc(1));
+        editor.move_cursor(0, 1);
+        editor.set(Position::Black);
+        assert_eq!(editor.next_player(), Position::Red);
+        assert_eq!(editor.finish().unwrap_err(), BoardError::NotAlternated);
+
+        editor.clear();
+        assert_eq!(editor.pos(3, 5), Position::Empty);
+        assert_eq!(editor.next_player(), Position::Red);
+        assert!(editor.finish().is_ok());
+    }
+}
@@ -0,0 +1,620 @@
+//! A line based text protocol for driving the AI from other programs, in the spirit of UCI.
+//!
+//! Columns are numbered 1 to 7 from the left, Red always moves first.
+//!
+//! ```text
+//! > cfi
+//! < id name connect-four-mcts 0.1.0
+//! < cfiok
+//! > setoption name threads value 4
+//! > position startpos moves 4 4 3
+//! > go movetime 1000
+//! < info iterations 51234 time 500 winrate 0.571 move 3 pv 3 4 4 5
+//! < bestmove 3
+//! ```
+//!
+//! `setoption name threads value <n>` searches `n` independent trees in parallel and merges
+//! their statistics, with an `iterations` limit shared between them. It defaults to one thread
+//! per CPU core.
+//!
+//! `setoption name seed value <n>` seeds the searches, so that `go iterations` gives the same
+//! `bestmove` every time for the same position and number of threads.
+//!
+//! `pv` is the expected line of play, see [`Search::principal_variation`]. Before it comes
+//! `proven win`, `proven loss` or `proven draw` once the search has solved the position.
+//!
+//! `exploration`, `selection` (`most-visits`, `highest-value` or `robust-max`), `drawvalue` and
+//! `rave` (0 turns it off) tune the search, see [`Search::exploration_factor`], [`MoveSelection`],
+//! [`Search::draw_value`] and [`Search::rave`].
+//!
+//! [`Search::principal_variation`]: search::Search::principal_variation
+//! [`Search::exploration_factor`]: search::Search::exploration_factor
+//! [`Search::draw_value`]: search::Search::draw_value
+//! [`Search::rave`]: search::Search::rave
+
+use std::{
+    fmt,
+    io::{self, BufRead, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use yamcts::GameState;
+
+use crate::{
+    connect_four::{CFGameState, CFMove},
+    notation::{self, NotationError},
+    search::{self, Budget, MoveSelection, Outcome, SearchConfig, SearchHandle, Snapshot},
+};
+
+/// How often `info` lines are sent while searching.
+const INFO_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, PartialEq, Eq)]
+enum CommandError {
+    UnknownCommand(String),
+    MissingArgument(&'static str),
+    InvalidNumber(String),
+    InvalidValue(String),
+    UnknownOption(String),
+    InvalidMove(String),
+    Notation(NotationError),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use CommandError::*;
+        match self {
+            UnknownCommand(cmd) => write!(f, "unknown command '{cmd}'"),
+            MissingArgument(what) => write!(f, "missing {what}"),
+            InvalidNumber(token) => write!(f, "invalid number '{token}'"),
+            InvalidValue(token) => write!(f, "invalid value '{token}'"),
+            UnknownOption(name) => write!(f, "unknown option '{name}'"),
+            InvalidMove(token) => {
+                write!(f, "invalid move '{token}', expected a column from 1 to 7")
+            }
+            Notation(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+/// The settings changed with `setoption`.
+#[derive(Debug, Clone, Copy)]
+struct Options {
+    threads: usize,
+    selection: MoveSelection,
+    search: SearchConfig,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            threads: search::default_threads(),
+            selection: MoveSelection::default(),
+            search: SearchConfig::default(),
+        }
+    }
+}
+
+struct Searching {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+/// The state of one protocol session, writing its responses to `W`.
+pub struct Engine<W: Write + Send + 'static> {
+    out: Arc<Mutex<W>>,
+    position: CFGameState,
+    options: Options,
+    searching: Option<Searching>,
+}
+
+impl<W: Write + Send + 'static> Engine<W> {
+    /// Starts a session at the empty board.
+    pub fn new(out: W) -> Self {
+        Self {
+            out: Arc::new(Mutex::new(out)),
+            position: CFGameState::default(),
+            options: Options::default(),
+            searching: None,
+        }
+    }
+
+    /// Handles one line of input. Returns false once the engine has been asked to quit.
+    pub fn handle_line(&mut self, line: &str) -> bool {
+        let mut tokens = line.split_whitespace();
+        let result = match tokens.next() {
+            Some("cfi") => {
+                send(
+                    &self.out,
+                    &format!(
+                        "id name {} {}",
+                        env!("CARGO_PKG_NAME"),
+                        env!("CARGO_PKG_VERSION")
+                    ),
+                );
+                send(&self.out, "cfiok");
+                Ok(())
+            }
+            Some("isready") => {
+                send(&self.out, "readyok");
+                Ok(())
+            }
+            Some("newgame") => {
+                self.stop();
+                self.position = CFGameState::default();
+                Ok(())
+            }
+            Some("position") => parse_position(tokens).map(|position| {
+                self.stop();
+                self.position = position;
+            }),
+            Some("setoption") => self.set_option(tokens),
+            Some("go") => parse_budget(tokens).map(|budget| self.go(budget)),
+            Some("stop") => {
+                self.stop();
+                Ok(())
+            }
+            Some("quit") => {
+                self.stop();
+                return false;
+            }
+            Some(cmd) => Err(CommandError::UnknownCommand(cmd.to_string())),
+            None => Ok(()),
+        };
+
+        if let Err(err) = result {
+            send(&self.out, &format!("info string error: {err}"));
+        }
+
+        true
+    }
+
+    fn set_option<'a>(
+        &mut self,
+        mut tokens: impl Iterator<Item = &'a str>,
+    ) -> Result<(), CommandError> {
+        if tokens.next() != Some("name") {
+            return Err(CommandError::MissingArgument("option name"));
+        }
+        let name = tokens
+            .next()
+            .ok_or(CommandError::MissingArgument("option name"))?;
+        if tokens.next() != Some("value") {
+            return Err(CommandError::MissingArgument("option value"));
+        }
+
+        match name {
+            "threads" => {
+                self.options.threads = parse_number::<usize>(tokens.next(), "threads")?.max(1);
+                Ok(())
+            }
+            "seed" => {
+                self.options.search.seed = Some(parse_number(tokens.next(), "seed")?);
+                Ok(())
+            }
+            "exploration" => {
+                let token = tokens.next();
+                let c: f64 = parse_number(token, "exploration")?;
+                if !(c.is_finite() && c >= 0.0) {
+                    return Err(CommandError::InvalidValue(token.unwrap().to_string()));
+                }
+                self.options.search.exploration_factor = c;
+                Ok(())
+            }
+            "selection" => {
+                let value = tokens
+                    .next()
+                    .ok_or(CommandError::MissingArgument("selection"))?;
+                self.options.selection = value
+                    .parse()
+                    .map_err(|_| CommandError::InvalidValue(value.to_string()))?;
+                Ok(())
+            }
+            "drawvalue" => {
+                let token = tokens.next();
+                let value: f64 = parse_number(token, "drawvalue")?;
+                if !(0.0..=1.0).contains(&value) {
+                    return Err(CommandError::InvalidValue(token.unwrap().to_string()));
+                }
+                self.options.search.draw_value = value;
+                Ok(())
+            }
+            "rave" => {
+                let token = tokens.next();
+                let k: f64 = parse_number(token, "rave")?;
+                if !(k.is_finite() && k >= 0.0) {
+                    return Err(CommandError::InvalidValue(token.unwrap().to_string()));
+                }
+                self.options.search.rave = (k > 0.0).then_some(k);
+                Ok(())
+            }
+            _ => Err(CommandError::UnknownOption(name.to_string())),
+        }
+    }
+
+    fn go(&mut self, budget: Budget) {
+        self.stop();
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let stop = stop.clone();
+            let out = self.out.clone();
+            let state = self.position.clone();
+            let options = self.options;
+            thread::spawn(move || search(state, budget, options, &stop, &out))
+        };
+
+        self.searching = Some(Searching { stop, handle });
+    }
+
+    /// Stops any running search, which answers with its `bestmove`.
+    fn stop(&mut self) {
+        if let Some(searching) = self.searching.take() {
+            searching.stop.store(true, Ordering::Relaxed);
+            let _ = searching.handle.join();
+        }
+    }
+}
+
+impl<W: Write + Send + 'static> Drop for Engine<W> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Reads commands from `input` until `quit` or end of input.
+pub fn run<W: Write + Send + 'static>(input: impl BufRead, out: W) -> io::Result<()> {
+    let mut engine = Engine::new(out);
+    for line in input.lines() {
+        if !engine.handle_line(&line?) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn send<W: Write>(out: &Mutex<W>, line: &str) {
+    let mut out = out.lock().unwrap();
+    // there is nobody left to report a broken pipe to
+    let _ = writeln!(out, "{line}").and_then(|_| out.flush());
+}
+
+/// Searches `state` on a thread per tree, sending `info` lines until `budget` is used up or
+/// `stop` is set, then the `bestmove`.
+fn search<W: Write>(
+    state: CFGameState,
+    budget: Budget,
+    options: Options,
+    stop: &AtomicBool,
+    out: &Mutex<W>,
+) {
+    if state.is_terminal_state().is_some() {
+        send(out, "bestmove none");
+        return;
+    }
+
+    let start = Instant::now();
+    let searches = (0..options.threads)
+        .map(|i| options.search.search(state.clone(), i))
+        .collect();
+    // even `go iterations 0` runs one iteration, which expands the root so that there is a best
+    // move
+    let budget = match budget {
+        Budget::Iterations(max) => Budget::Iterations(max.max(1)),
+        budget => budget,
+    };
+    let handle = SearchHandle::spawn_parallel(searches, budget);
+
+    let mut next_info = start + INFO_INTERVAL;
+    while !handle.is_finished() && !stop.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(5));
+        if Instant::now() >= next_info {
+            next_info += INFO_INTERVAL;
+            send(
+                out,
+                &info_line(&handle.snapshot(), options.selection, start),
+            );
+        }
+    }
+
+    let snapshot = handle.stop();
+    send(out, &info_line(&snapshot, options.selection, start));
+    match snapshot.select(options.selection) {
+        Some(best) => send(out, &format!("bestmove {}", best.action.col + 1)),
+        None => send(out, "bestmove none"),
+    }
+}
+
+fn info_line(snapshot: &Snapshot<CFMove>, selection: MoveSelection, start: Instant) -> String {
+    let mut line = format!(
+        "info iterations {} time {}",
+        snapshot.iterations,
+        start.elapsed().as_millis()
+    );
+    if let Some(best) = snapshot.select(selection) {
+        line += &format!(" winrate {:.3} move {}", best.win_rate, best.action.col + 1);
+        match best.proven {
+            Some(Outcome::Win) => line += " proven win",
+            Some(Outcome::Loss) => line += " proven loss",
+            Some(Outcome::Draw) => line += " proven draw",
+            None => {}
+        }
+    }
+    if !snapshot.pv.is_empty() {
+        line += " pv";
+        for action in &snapshot.pv {
+            line += &format!(" {}", action.col + 1);
+        }
+    }
+    line
+}
+
+fn parse_number<T: std::str::FromStr>(
+    token: Option<&str>,
+    what: &'static str,
+) -> Result<T, CommandError> {
+    let token = token.ok_or(CommandError::MissingArgument(what))?;
+    token
+        .parse()
+        .map_err(|_| CommandError::InvalidNumber(token.to_string()))
+}
+
+fn parse_position<'a>(
+    mut tokens: impl Iterator<Item = &'a str>,
+) -> Result<CFGameState, CommandError> {
+    match tokens.next() {
+        Some("startpos") => {}
+        Some(other) => return Err(CommandError::UnknownCommand(other.to_string())),
+        None => return Err(CommandError::MissingArgument("position")),
+    }
+
+    match tokens.next() {
+        Some("moves") => {}
+        Some(other) => return Err(CommandError::UnknownCommand(other.to_string())),
+        None => return Ok(CFGameState::default()),
+    }
+
+    // every token is one move, so that `44 5` is not taken for three of them
+    let cols = tokens
+        .map(|token| {
+            let mut chars = token.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => notation::parse_col(c)
+                    .ok_or(CommandError::Notation(NotationError::InvalidColumn(c))),
+                _ => Err(CommandError::InvalidMove(token.to_string())),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    notation::play_cols(&CFGameState::default(), cols).map_err(CommandError::Notation)
+}
+
+fn parse_budget<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Result<Budget, CommandError> {
+    match tokens.next() {
+        Some("movetime") => Ok(Budget::Time(Duration::from_millis(parse_number(
+            tokens.next(),
+            "movetime",
+        )?))),
+        Some("iterations") => Ok(Budget::Iterations(parse_number(
+            tokens.next(),
+            "iterations",
+        )?)),
+        Some("infinite") => Ok(Budget::Infinite),
+        Some(other) => Err(CommandError::UnknownCommand(other.to_string())),
+        None => Err(CommandError::MissingArgument("search limit")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuf {
+        fn lines(&self) -> Vec<String> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(String::from)
+                .collect()
+        }
+    }
+
+    /// Waits for the running search to reach its own limit.
+    fn finish(engine: &mut Engine<SharedBuf>) {
+        if let Some(searching) = engine.searching.take() {
+            searching.handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn handshake() {
+        let buf = SharedBuf::default();
+        let mut engine = Engine::new(buf.clone());
+        assert!(engine.handle_line("cfi"));
+        assert!(engine.handle_line("isready"));
+        assert!(!engine.handle_line("quit"));
+
+        let lines = buf.lines();
+        assert!(lines[0].starts_with("id name connect-four-mcts"));
+        assert_eq!(lines[1..], ["cfiok", "readyok"]);
+    }
+
+    #[test]
+    fn plays_winning_move() {
+        let buf = SharedBuf::default();
+        let mut engine = Engine::new(buf.clone());
+        engine.handle_line("position startpos moves 4 5 4 5 4 5");
+        engine.handle_line("go iterations 3000");
+        finish(&mut engine);
+
+        let lines = buf.lines();
+        assert_eq!(lines.last().unwrap(), "bestmove 4");
+        assert!(lines.iter().any(|l| l.starts_with("info iterations 3000 ")));
+        assert!(lines[lines.len() - 2].ends_with("move 4 proven win pv 4"));
+    }
+
+    #[test]
+    fn stop_ends_infinite_search() {
+        let buf = SharedBuf::default();
+        let mut engine = Engine::new(buf.clone());
+        engine.handle_line("go infinite");
+        thread::sleep(Duration::from_millis(50));
+        engine.handle_line("stop");
+
+        let lines = buf.lines();
+        assert!(lines.last().unwrap().starts_with("bestmove "));
+    }
+
+    #[test]
+    fn rejects_bad_positions() {
+        assert_eq!(
+            parse_position("startpos moves 4 8".split_whitespace()).unwrap_err(),
+            CommandError::Notation(NotationError::InvalidColumn('8'))
+        );
+        assert_eq!(
+            parse_position("startpos moves 44 5".split_whitespace()).unwrap_err(),
+            CommandError::InvalidMove("44".into())
+        );
+        assert_eq!(
+            parse_position("startpos moves 4 10".split_whitespace()).unwrap_err(),
+            CommandError::InvalidMove("10".into())
+        );
+        assert_eq!(
+            parse_position("startpos moves 4 4 5".split_whitespace())
+                .unwrap()
+                .rows(),
+            notation::parse("445").unwrap().rows()
+        );
+        assert_eq!(
+            parse_position("startpos moves 1 1 1 1 1 1 1".split_whitespace()).unwrap_err(),
+            CommandError::Notation(NotationError::ColumnFull { index: 6, col: 0 })
+        );
+        assert_eq!(
+            parse_position("startpos moves 1 2 1 2 1 2 1 2".split_whitespace()).unwrap_err(),
+            CommandError::Notation(NotationError::GameOver { index: 7 })
+        );
+        assert_eq!(
+            parse_budget("movetime abc".split_whitespace()).unwrap_err(),
+            CommandError::InvalidNumber("abc".into())
+        );
+    }
+
+    #[test]
+    fn small_iteration_limits() {
+        for iterations in [0, 1, 4] {
+            let buf = SharedBuf::default();
+            let mut engine = Engine::new(buf.clone());
+            engine.handle_line("setoption name threads value 4");
+            engine.handle_line(&format!("go iterations {iterations}"));
+            finish(&mut engine);
+
+            let lines = buf.lines();
+            let col = lines.last().unwrap().strip_prefix("bestmove ").unwrap();
+            assert!(notation::parse(col).is_ok(), "bestmove {col}");
+        }
+    }
+
+    #[test]
+    fn reports_finished_games() {
+        let buf = SharedBuf::default();
+        let mut engine = Engine::new(buf.clone());
+        engine.handle_line("position startpos moves 1 2 1 2 1 2 1");
+        engine.handle_line("go movetime 10");
+        finish(&mut engine);
+
+        assert_eq!(buf.lines(), ["bestmove none"]);
+    }
+
+    #[test]
+    fn parallel_search() {
+        let buf = SharedBuf::default();
+        let mut engine = Engine::new(buf.clone());
+        engine.handle_line("setoption name threads value 3");
+        engine.handle_line("position startpos moves 4 5 4 5 4 5");
+        engine.handle_line("go iterations 3001");
+        finish(&mut engine);
+
+        let lines = buf.lines();
+        assert_eq!(lines.last().unwrap(), "bestmove 4");
+        assert!(lines.iter().any(|l| l.starts_with("info iterations 3001 ")));
+
+        engine.handle_line("setoption name hash value 16");
+        engine.handle_line("setoption name threads value many");
+        engine.handle_line("setoption name selection value best");
+        engine.handle_line("setoption name exploration value -1");
+        engine.handle_line("setoption name exploration value NaN");
+        engine.handle_line("setoption name drawvalue value 2");
+        engine.handle_line("setoption name rave value -1000");
+        let lines = buf.lines();
+        assert_eq!(
+            lines[lines.len() - 7..],
+            [
+                "info string error: unknown option 'hash'",
+                "info string error: invalid number 'many'",
+                "info string error: invalid value 'best'",
+                "info string error: invalid value '-1'",
+                "info string error: invalid value 'NaN'",
+                "info string error: invalid value '2'",
+                "info string error: invalid value '-1000'"
+            ]
+        );
+    }
+
+    #[test]
+    fn seeded_search() {
+        let run = || {
+            let buf = SharedBuf::default();
+            let mut engine = Engine::new(buf.clone());
+            engine.handle_line("setoption name threads value 2");
+            engine.handle_line("setoption name seed value 5");
+            engine.handle_line("go iterations 2000");
+            finish(&mut engine);
+
+            // everything but the time of the final report
+            let lines = buf.lines();
+            let (before, after) = lines[lines.len() - 2].split_once(" time ").unwrap();
+            let after = after.split_once(' ').unwrap().1;
+            (format!("{before} {after}"), lines.last().unwrap().clone())
+        };
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn search_options() {
+        let buf = SharedBuf::default();
+        let mut engine = Engine::new(buf.clone());
+        engine.handle_line("setoption name exploration value 0.5");
+        engine.handle_line("setoption name selection value robust-max");
+        engine.handle_line("setoption name drawvalue value 0.5");
+        assert_eq!(engine.options.search.exploration_factor, 0.5);
+        assert_eq!(engine.options.selection, MoveSelection::RobustMax);
+        assert_eq!(engine.options.search.draw_value, 0.5);
+        engine.handle_line("setoption name rave value 1000");
+        assert_eq!(engine.options.search.rave, Some(1000.0));
+        engine.handle_line("setoption name rave value 0");
+        assert_eq!(engine.options.search.rave, None);
+        engine.handle_line("setoption name rave value 1000");
+
+        engine.handle_line("position startpos moves 4 5 4 5 4 5");
+        engine.handle_line("go iterations 2000");
+        finish(&mut engine);
+        assert_eq!(buf.lines().last().unwrap(), "bestmove 4");
+    }
+}
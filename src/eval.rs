@@ -0,0 +1,260 @@
+//! Static evaluation of positions, and a depth-limited alpha-beta search on top of it.
+//!
+//! The evaluation counts what each player could still build: every window of four cells that
+//! holds only one player's discs, weighted by how many discs are already in it, plus the discs
+//! in the center column and the threats on rows that favor their owner (see
+//! [`crate::threats`]). Scores are from the point of view of the side to move, positive when it
+//! is better off.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use yamcts::GameState;
+
+use crate::connect_four::{CFGameState, CFMove, Position};
+
+/// The score of a won position. Wins found by [`Evaluator::alpha_beta`] score slightly less for
+/// every ply it takes to get there, see [`plies_to_win`].
+pub const WIN_SCORE: i32 = 1_000_000;
+
+/// Every window of four cells as (col, row) steps: horizontal, vertical and both diagonals.
+const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+/// Columns from the center outwards, which is the order alpha-beta tries them in.
+const MOVE_ORDER: [usize; 7] = [3, 2, 4, 1, 5, 0, 6];
+
+/// Weights of the features that make up the evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Evaluator {
+    /// Per window with three discs of one player and an empty cell.
+    pub three: i32,
+    /// Per window with two discs of one player and two empty cells.
+    pub two: i32,
+    /// Per disc in the center column.
+    pub center: i32,
+    /// Per threat on a row that favors its owner, odd for Red and even for Black.
+    pub parity_threat: i32,
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self {
+            three: 8,
+            two: 2,
+            center: 3,
+            parity_threat: 20,
+        }
+    }
+}
+
+impl Evaluator {
+    /// Scores `state` for the side to move. Finished games score [`WIN_SCORE`], its negation
+    /// or 0 for a draw.
+    pub fn evaluate(&self, state: &CFGameState) -> i32 {
+        if let Some(result) = state.is_terminal_state() {
+            return match result {
+                Position::Empty => 0,
+                // the player who just moved won
+                _ => -WIN_SCORE,
+            };
+        }
+
+        let player = state.next_player();
+        self.features(state, player) - self.features(state, player.opponent())
+    }
+
+    /// Maps the evaluation to the chance that the player who just moved wins, which is what
+    /// [`Search::evaluate_leaves`](crate::search::Search::evaluate_leaves) expects.
+    pub fn win_probability(&self, state: &CFGameState) -> f64 {
+        let score = -self.evaluate(state) as f64;
+        1.0 / (1.0 + (-score / 50.0).exp())
+    }
+
+    /// Searches `depth` plies ahead and returns the best move with its score, or `None` if the
+    /// game is over.
+    pub fn alpha_beta(&self, state: &CFGameState, depth: u32) -> Option<(CFMove, i32)> {
+        self.alpha_beta_until(state, depth, &AtomicBool::new(false))
+    }
+
+    /// Like [`Evaluator::alpha_beta`], but gives up and returns `None` as soon as `stop` is set,
+    /// which it checks at every position. The cost of a search grows quickly with `depth`: ten
+    /// plies from the middle of a game can take half a minute.
+    pub fn alpha_beta_until(
+        &self,
+        state: &CFGameState,
+        depth: u32,
+        stop: &AtomicBool,
+    ) -> Option<(CFMove, i32)> {
+        if state.is_terminal_state().is_some() {
+            return None;
+        }
+
+        let color = state.next_player();
+        let mut best = None;
+        let mut alpha = -WIN_SCORE - 1;
+        for col in MOVE_ORDER.into_iter().filter(|&col| state.can_play(col)) {
+            let action = CFMove { color, col };
+            let score = -self.negamax(
+                &state.apply_move(action),
+                depth.saturating_sub(1),
+                -WIN_SCORE - 1,
+                -alpha,
+                1,
+                stop,
+            );
+            if stop.load(Ordering::Relaxed) {
+                return None;
+            }
+            if best.is_none() || score > alpha {
+                alpha = score;
+                best = Some((action, score));
+            }
+        }
+        best
+    }
+
+    /// The score of `state` for the side to move, or anything at all once `stop` is set.
+    fn negamax(
+        &self,
+        state: &CFGameState,
+        depth: u32,
+        mut alpha: i32,
+        beta: i32,
+        ply: i32,
+        stop: &AtomicBool,
+    ) -> i32 {
+        if stop.load(Ordering::Relaxed) {
+            return 0;
+        }
+        if let Some(result) = state.is_terminal_state() {
+            return match result {
+                Position::Empty => 0,
+                _ => -(WIN_SCORE - ply),
+            };
+        }
+        if depth == 0 {
+            return self.evaluate(state);
+        }
+
+        let color = state.next_player();
+        for col in MOVE_ORDER.into_iter().filter(|&col| state.can_play(col)) {
+            let child = state.apply_move(CFMove { color, col });
+            let score = -self.negamax(&child, depth - 1, -beta, -alpha, ply + 1, stop);
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+
+    fn features(&self, state: &CFGameState, color: Position) -> i32 {
+        let mut score = 0;
+
+        for (dc, dr) in DIRECTIONS {
+            for col in 0..7_isize {
+                for row in 0..6_isize {
+                    let cells = (0..4).map(|i| (col + dc * i, row + dr * i));
+                    if !cells
+                        .clone()
+                        .all(|(c, r)| (0..7).contains(&c) && (0..6).contains(&r))
+                    {
+                        continue;
+                    }
+
+                    let (mut own, mut empty) = (0, 0);
+                    for (c, r) in cells {
+                        match state.pos(c as usize, r as usize) {
+                            p if p == color => own += 1,
+                            Position::Empty => empty += 1,
+                            _ => {}
+                        }
+                    }
+                    score += match (own, empty) {
+                        (3, 1) => self.three,
+                        (2, 2) => self.two,
+                        _ => 0,
+                    };
+                }
+            }
+        }
+
+        score += self.center * (0..6).filter(|&row| state.pos(3, row) == color).count() as i32;
+
+        let good_parity = color == Position::Red;
+        score += self.parity_threat
+            * state
+                .threats(color)
+                .iter()
+                .filter(|t| t.is_odd() == good_parity)
+                .count() as i32;
+
+        score
+    }
+}
+
+/// How many plies a score from [`Evaluator::alpha_beta`] takes to end the game, counting the
+/// move being scored. Negative scores are losses, and `None` means no forced result was found.
+pub fn plies_to_win(score: i32) -> Option<u32> {
+    let plies = WIN_SCORE - score.abs();
+    (1..=42).contains(&plies).then_some(plies as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation;
+
+    #[test]
+    fn evaluation() {
+        let evaluator = Evaluator::default();
+        assert_eq!(evaluator.evaluate(&CFGameState::default()), 0);
+
+        // Red took the center, so Black to move is behind
+        let state = notation::parse("4").unwrap();
+        assert!(evaluator.evaluate(&state) < 0);
+        let state = notation::parse("41").unwrap();
+        assert!(evaluator.evaluate(&state) > 0);
+
+        let won = notation::parse("1212121").unwrap();
+        assert_eq!(evaluator.evaluate(&won), -WIN_SCORE);
+        assert!(evaluator.win_probability(&won) > 0.99);
+    }
+
+    #[test]
+    fn alpha_beta() {
+        let evaluator = Evaluator::default();
+
+        // Red wins at once in the first column
+        let (action, score) = evaluator
+            .alpha_beta(&notation::parse("121212").unwrap(), 4)
+            .unwrap();
+        assert_eq!(action.col, 0);
+        assert_eq!(plies_to_win(score), Some(1));
+
+        // Black has to block
+        let (action, _) = evaluator
+            .alpha_beta(&notation::parse("12121").unwrap(), 4)
+            .unwrap();
+        assert_eq!(action.col, 0);
+
+        // Red makes an open three on the bottom row and wins with its next move
+        let (action, score) = evaluator
+            .alpha_beta(&notation::parse("3747").unwrap(), 4)
+            .unwrap();
+        assert!(action.col == 1 || action.col == 4);
+        assert_eq!(plies_to_win(score), Some(3));
+
+        assert!(evaluator
+            .alpha_beta(&notation::parse("1212121").unwrap(), 4)
+            .is_none());
+        // a stopped search has no answer
+        let stop = AtomicBool::new(true);
+        assert!(evaluator
+            .alpha_beta_until(&notation::parse("121212").unwrap(), 4, &stop)
+            .is_none());
+        assert_eq!(
+            plies_to_win(evaluator.evaluate(&CFGameState::default())),
+            None
+        );
+    }
+}
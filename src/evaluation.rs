@@ -0,0 +1,223 @@
+//! The evaluation bar next to the board, fed by a background search of the current position.
+//!
+//! The Monte Carlo search gives the chances of each side, and a depth-limited alpha-beta search
+//! running alongside it replaces them with an exact result once it finds a forced win.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use connect_four_mcts::{
+    connect_four::{CFGameState, CFMove, Position},
+    eval::{self, Evaluator},
+    search::{Budget, Search, SearchHandle},
+    GameState,
+};
+
+/// Deep enough to spot most forced wins near the end of a game. The last depths can take half a
+/// minute from the middle of a game, but the search stops as soon as the position changes.
+const SOLVER_DEPTH: u32 = 10;
+
+/// The most positions in the tree of the search, which runs for as long as the position is on
+/// the board. At about 200 bytes each, that keeps it to 100 MB.
+const MAX_NODES: usize = 500_000;
+
+pub struct Evaluation {
+    cells: Vec<Position>,
+    game: CFGameState,
+    search: Option<SearchHandle<CFGameState>>,
+    solver_stop: Arc<AtomicBool>,
+    solved: Arc<Mutex<Option<i32>>>,
+}
+
+impl Evaluation {
+    pub fn new(game: &CFGameState) -> Self {
+        let mut evaluation = Self {
+            cells: Vec::new(),
+            game: game.clone(),
+            search: None,
+            solver_stop: Arc::new(AtomicBool::new(false)),
+            solved: Arc::new(Mutex::new(None)),
+        };
+        evaluation.start(game);
+        evaluation
+    }
+
+    /// Restarts the analysis whenever the position has changed since the last call.
+    pub fn poll(&mut self, game: &CFGameState) {
+        if cells(game) != self.cells {
+            self.start(game);
+        }
+    }
+
+    fn start(&mut self, game: &CFGameState) {
+        self.solver_stop.store(true, Ordering::Relaxed);
+        self.cells = cells(game);
+        self.game = game.clone();
+        self.search = None;
+        self.solver_stop = Arc::new(AtomicBool::new(false));
+        self.solved = Arc::new(Mutex::new(None));
+
+        if game.is_terminal_state().is_some() {
+            return;
+        }
+
+        self.search = Some(SearchHandle::spawn(
+            Search::new(game.clone()).max_nodes(MAX_NODES),
+            Budget::Infinite,
+        ));
+
+        let stop = self.solver_stop.clone();
+        let solved = self.solved.clone();
+        let game = game.clone();
+        thread::spawn(move || {
+            let evaluator = Evaluator::default();
+            for depth in (2..=SOLVER_DEPTH).step_by(2) {
+                let Some((_, score)) = evaluator.alpha_beta_until(&game, depth, &stop) else {
+                    return;
+                };
+                // a deeper search would only find the same forced result
+                if eval::plies_to_win(score).is_some() {
+                    *solved.lock().unwrap() = Some(score);
+                    return;
+                }
+            }
+        });
+    }
+
+    /// The line of play the search expects from the current position, as far as it has gone.
+    pub fn pv(&self) -> Vec<CFMove> {
+        self.search
+            .as_ref()
+            .map_or_else(Vec::new, |s| s.snapshot().pv)
+    }
+
+    /// The share of the bar that belongs to Red, from 0 to 1, and the label to show under it.
+    pub fn bar(&self) -> (f64, String) {
+        if let Some(result) = self.game.is_terminal_state() {
+            return match result {
+                Position::Red => (1.0, "Red won".to_string()),
+                Position::Black => (0.0, "Black won".to_string()),
+                Position::Empty => (0.5, "Draw".to_string()),
+            };
+        }
+
+        let to_move = self.game.next_player();
+        if let Some(score) = *self.solved.lock().unwrap() {
+            let plies = eval::plies_to_win(score).unwrap();
+            let winner = if score > 0 {
+                to_move
+            } else {
+                to_move.opponent()
+            };
+            // count the winner's own moves, the first ply is the side to move
+            let moves = if winner == to_move {
+                plies.div_ceil(2)
+            } else {
+                plies / 2
+            };
+            let share = if winner == Position::Red { 1.0 } else { 0.0 };
+            let name = if winner == Position::Red {
+                "Red"
+            } else {
+                "Black"
+            };
+            return (share, format!("{name} wins in {moves}"));
+        }
+
+        let Some(best) = self.search.as_ref().and_then(|s| s.snapshot().best()) else {
+            return (0.5, "thinking".to_string());
+        };
+        let red = if to_move == Position::Red {
+            best.win_rate
+        } else {
+            1.0 - best.win_rate
+        };
+        if red >= 0.5 {
+            (red, format!("Red {:.0}%", red * 100.0))
+        } else {
+            (red, format!("Black {:.0}%", (1.0 - red) * 100.0))
+        }
+    }
+}
+
+impl Drop for Evaluation {
+    fn drop(&mut self) {
+        self.solver_stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn cells(game: &CFGameState) -> Vec<Position> {
+    (0..6)
+        .flat_map(|row| (0..7).map(move |col| game.pos(col, row)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use connect_four_mcts::notation;
+
+    use super::*;
+
+    /// The bar once its label is no longer `skip`, or after a few seconds.
+    fn settled(evaluation: &Evaluation, skip: impl Fn(&str) -> bool) -> (f64, String) {
+        let start = Instant::now();
+        loop {
+            let bar = evaluation.bar();
+            if !skip(&bar.1) || start.elapsed() > Duration::from_secs(5) {
+                return bar;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn finished_games() {
+        let evaluation = Evaluation::new(&notation::parse("1212121").unwrap());
+        assert_eq!(evaluation.bar(), (1.0, "Red won".to_string()));
+        assert!(evaluation.pv().is_empty());
+
+        let evaluation = Evaluation::new(&notation::parse("31315171").unwrap());
+        assert_eq!(evaluation.bar(), (0.0, "Black won".to_string()));
+    }
+
+    #[test]
+    fn forced_win() {
+        // Red wins with a fourth disc in column 1
+        let evaluation = Evaluation::new(&notation::parse("121212").unwrap());
+        let bar = settled(&evaluation, |label| !label.contains("wins"));
+        assert_eq!(bar, (1.0, "Red wins in 1".to_string()));
+
+        // a third disc next to columns 2 and 3 is open at both ends, and wins with Red's next move
+        let evaluation = Evaluation::new(&notation::parse("2737").unwrap());
+        let bar = settled(&evaluation, |label| !label.contains("wins"));
+        assert_eq!(bar, (1.0, "Red wins in 2".to_string()));
+    }
+
+    #[test]
+    fn poll_restarts() {
+        let mut evaluation = Evaluation::new(&CFGameState::default());
+        let (red, label) = settled(&evaluation, |label| label == "thinking");
+        assert!((0.0..=1.0).contains(&red));
+        assert!(label.starts_with("Red ") || label.starts_with("Black "));
+
+        // the same position keeps the search going
+        let iterations = |e: &Evaluation| e.search.as_ref().unwrap().snapshot().iterations;
+        let before = iterations(&evaluation);
+        evaluation.poll(&CFGameState::default());
+        assert!(iterations(&evaluation) >= before);
+
+        // a new position stops the alpha-beta search of the old one
+        let solver_stop = evaluation.solver_stop.clone();
+        evaluation.poll(&notation::parse("1212121").unwrap());
+        assert!(solver_stop.load(Ordering::Relaxed));
+        assert!(evaluation.search.is_none());
+        assert_eq!(evaluation.bar(), (1.0, "Red won".to_string()));
+    }
+}
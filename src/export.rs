@@ -0,0 +1,215 @@
+//! Search trees written out for inspection, as Graphviz DOT or JSON.
+//!
+//! Both take a [`TreeNode`] from [`Search::tree`](crate::Search::tree), pruned to taste. Every
+//! position is labeled with the move into it (columns counted from 1), its visits, its win rate
+//! for the player who made that move and its board. A DOT file renders with
+//! `dot -Tsvg tree.dot -o tree.svg`, and the JSON looks like this, with `move` null at the root
+//! and `proven` null unless the solver settled the position:
+//!
+//! ```json
+//! {
+//!   "move": { "color": "red", "col": 4 },
+//!   "visits": 1200,
+//!   "win_rate": 0.56,
+//!   "proven": "win",
+//!   "board": [".......", ".......", ".......", ".......", ".......", "...R..."],
+//!   "next_player": "black",
+//!   "children": []
+//! }
+//! ```
+
+use std::fmt::Write;
+
+use crate::{
+    connect_four::{CFGameState, Position},
+    search::{Outcome, TreeNode},
+};
+
+/// Writes the tree as a Graphviz digraph, one box per position.
+pub fn dot(tree: &TreeNode<CFGameState>) -> String {
+    let mut out = String::from("digraph tree {\n    node [shape=box, fontname=\"monospace\"];\n");
+    let mut next_id = 0;
+    write_dot(tree, None, &mut next_id, &mut out);
+    out.push_str("}\n");
+    out
+}
+
+fn write_dot(
+    node: &TreeNode<CFGameState>,
+    parent: Option<usize>,
+    next_id: &mut usize,
+    out: &mut String,
+) {
+    let id = *next_id;
+    *next_id += 1;
+
+    let mut label = match node.action {
+        Some(action) => format!("{} {}\\l", color_name(action.color), action.col + 1),
+        None => String::from("root\\l"),
+    };
+    let _ = write!(
+        label,
+        "{} visits, {:.1}%\\l",
+        node.visits,
+        node.win_rate * 100.0
+    );
+    if let Some(proven) = node.proven {
+        let _ = write!(label, "proven {}\\l", outcome_name(proven));
+    }
+    for row in node.state.rows() {
+        label.push_str(&row);
+        label.push_str("\\l");
+    }
+
+    let _ = writeln!(out, "    n{id} [label=\"{label}\"];");
+    if let Some(parent) = parent {
+        let _ = writeln!(out, "    n{parent} -> n{id};");
+    }
+    for child in &node.children {
+        write_dot(child, Some(id), next_id, out);
+    }
+}
+
+/// Writes the tree as a JSON object, with the children of each position nested in it.
+pub fn json(tree: &TreeNode<CFGameState>) -> String {
+    let mut out = String::new();
+    write_json(tree, &mut out);
+    out.push('\n');
+    out
+}
+
+fn write_json(node: &TreeNode<CFGameState>, out: &mut String) {
+    out.push_str("{\"move\":");
+    match node.action {
+        Some(action) => {
+            let _ = write!(
+                out,
+                "{{\"color\":\"{}\",\"col\":{}}}",
+                color_name(action.color),
+                action.col + 1
+            );
+        }
+        None => out.push_str("null"),
+    }
+    let _ = write!(
+        out,
+        ",\"visits\":{},\"win_rate\":{},\"proven\":",
+        node.visits, node.win_rate
+    );
+    match node.proven {
+        Some(proven) => {
+            let _ = write!(out, "\"{}\"", outcome_name(proven));
+        }
+        None => out.push_str("null"),
+    }
+
+    out.push_str(",\"board\":[");
+    for (i, row) in node.state.rows().iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        let _ = write!(out, "\"{row}\"");
+    }
+    let _ = write!(
+        out,
+        "],\"next_player\":\"{}\",\"children\":[",
+        color_name(node.state.next_player())
+    );
+    for (i, child) in node.children.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_json(child, out);
+    }
+    out.push_str("]}");
+}
+
+fn color_name(color: Position) -> &'static str {
+    match color {
+        Position::Red => "red",
+        Position::Black => "black",
+        Position::Empty => "empty",
+    }
+}
+
+fn outcome_name(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Win => "win",
+        Outcome::Loss => "loss",
+        Outcome::Draw => "draw",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{notation, Search};
+
+    use super::*;
+
+    fn tree() -> TreeNode<CFGameState> {
+        // Red wins with a fourth disc in column 1
+        let mut search = Search::new(notation::parse("121212").unwrap()).seed(3);
+        search.run_iterations(500);
+        let mut tree = search.tree(1);
+        tree.prune(1);
+        tree
+    }
+
+    #[test]
+    fn dot_labels() {
+        let dot = dot(&tree());
+        assert!(dot.starts_with("digraph tree {"));
+        assert!(dot.contains("n0 [label=\"root\\l500 visits"));
+        assert!(dot.contains("red 1\\l"));
+        assert!(dot.contains("proven win\\l"));
+        assert!(dot.contains("n0 -> n1;"));
+        // the board after the winning move
+        assert!(dot.contains("R......\\lRB.....\\lRB.....\\lRB.....\\l"));
+        assert!(dot.trim_end().ends_with('}'));
+    }
+
+    #[test]
+    fn json_nesting() {
+        let tree = tree();
+        let json = json(&tree);
+        assert!(json.starts_with("{\"move\":null,\"visits\":500,"));
+        assert!(json.contains("\"move\":{\"color\":\"red\",\"col\":1},"));
+        assert!(json.contains("\"proven\":\"win\""));
+        assert!(json.contains("\"next_player\":\"black\""));
+        assert_eq!(json.matches("\"board\":").count(), tree.size());
+        assert_eq!(json.matches('{').count(), json.matches('}').count());
+    }
+
+    /// The moves and positions in the JSON read back as their serde form.
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_matches_serde() {
+        use crate::CFMove;
+
+        fn check(node: &TreeNode<CFGameState>, value: &serde_json::Value) {
+            let action = serde_json::from_value::<Option<CFMove>>(value["move"].clone()).unwrap();
+            assert_eq!(action, node.action);
+
+            let state = serde_json::from_value::<CFGameState>(serde_json::json!({
+                "board": value["board"],
+                "next_player": value["next_player"],
+            }))
+            .unwrap();
+            assert_eq!(state.rows(), node.state.rows());
+            assert_eq!(state.next_player(), node.state.next_player());
+            assert_eq!(
+                serde_json::to_value(&node.state).unwrap()["board"],
+                value["board"]
+            );
+
+            let children = value["children"].as_array().unwrap();
+            assert_eq!(children.len(), node.children.len());
+            for (child, value) in node.children.iter().zip(children) {
+                check(child, value);
+            }
+        }
+
+        let tree = tree();
+        check(&tree, &serde_json::from_str(&json(&tree)).unwrap());
+    }
+}
@@ -0,0 +1,57 @@
+//! Connect Four with a Monte Carlo Tree Search AI.
+//!
+//! The game itself is [`CFGameState`], which implements the [`yamcts::GameState`] trait, and the
+//! AI is a [`Search`] over it. On top of those sit a text protocol for other programs to drive
+//! the engine ([`engine`]), search trees written out for inspection ([`export`]), a JSON API
+//! over HTTP (`server`) and two-player games over TCP (`net`).
+//!
+//! ```
+//! use connect_four_mcts::{notation, Search};
+//!
+//! // Red has three in the first column
+//! let state = notation::parse("121212").unwrap();
+//!
+//! let mut search = Search::new(state);
+//! search.run_iterations(2000);
+//! assert_eq!(search.best().unwrap().action.col, 0);
+//! ```
+//!
+//! # Features
+//!
+//! The game, the search, the notation, the tree export and the engine protocol only depend on
+//! `yamcts` and `rand`. Everything else is optional:
+//!
+//! - `tui` builds the terminal UI binary, and implies `net`. On by default.
+//! - `net` enables the `net` module for two-player games over TCP.
+//! - `serde` implements `Serialize` and `Deserialize` for the game types and [`record`].
+//! - `server` enables the `server` module, the HTTP JSON API, and implies `serde`. On by
+//!   default.
+
+#![warn(missing_docs)]
+
+pub mod connect_four;
+pub mod engine;
+pub mod eval;
+pub mod export;
+#[cfg(feature = "net")]
+pub mod net;
+pub mod notation;
+pub mod record;
+pub mod rollout;
+pub mod search;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod threats;
+
+pub use connect_four::{CFGameState, CFMove, Position};
+pub use eval::Evaluator;
+pub use record::{GameRecord, GameResult};
+pub use rollout::HeuristicRollout;
+pub use search::{
+    Budget, MoveSelection, MoveStats, Rollout, Search, SearchConfig, SearchHandle, Snapshot,
+    TreeNode,
+};
+pub use threats::Threat;
+pub use yamcts::GameState;
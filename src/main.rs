@@ -1,11 +1,29 @@
 use std::{
     collections::{HashSet, VecDeque},
     io::{self},
+    time::Duration,
 };
-mod connect_four;
+mod analysis;
+mod config;
+mod dump;
+mod editor;
+mod evaluation;
 mod tui;
+mod watch;
 
-use connect_four::{CFGameState, CFMove, Position};
+use analysis::Analysis;
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand};
+use config::{AiArgs, Settings};
+use connect_four_mcts::{
+    connect_four::{self, CFGameState, CFMove, Position},
+    engine,
+    net::{self, Message, NetEvent},
+    notation,
+    search::{self, Budget, Outcome, Search, SearchConfig, SearchHandle, Snapshot, TreeNode},
+};
+use dump::TreeOptions;
+use editor::Editor;
+use evaluation::Evaluation;
 use ratatui::{
     crossterm::event::KeyEvent,
     style::{Style, Stylize},
@@ -14,22 +32,94 @@ use ratatui::{
     Frame,
 };
 use tui::{Spinner, SpinnerState};
-use yamcts::{rng::DefaultRng, BestResultHandle, GameState};
+use watch::{AiConfig, Watch};
+use yamcts::GameState;
+
+/// How long the AI thinks about a move or a hint, unless it is given a number of iterations.
+const THINK_TIME: Duration = Duration::from_secs(1);
+
+/// How many moves of the expected line of play are previewed on the board.
+const PV_PREVIEW: usize = 4;
+
+/// The longest the AI keeps searching during the human's turn. Its trees stop growing at a node
+/// limit well before that, see [`AiConfig::searches`].
+const PONDER_TIME: Duration = Duration::from_secs(20);
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 enum AppState {
     ColumnSelection,
     AiThinking,
+    WaitingForOpponent,
     GameOver,
+    /// Setting up a position in the board editor.
+    Editing,
+}
+
+enum Mode {
+    VsAi,
+    Remote(net::Connection),
+    /// Another human taking turns at the same keyboard.
+    HotSeat,
+    /// Two AI players against each other.
+    Watch(Box<Watch>),
+    /// Moves for either side, with the engine searching the position on the board.
+    Analysis(Box<Analysis>),
+}
+
+/// The glyph and color used to draw a player's discs.
+fn disc_style(player: Position) -> (&'static str, ratatui::style::Color) {
+    use ratatui::style::Color;
+    match player {
+        Position::Red => ("O", Color::LightBlue),
+        Position::Black => ("X", Color::LightRed),
+        Position::Empty => (" ", Color::Gray),
+    }
+}
+
+fn player_name(player: Position) -> &'static str {
+    match player {
+        Position::Red => "Red (O)",
+        Position::Black => "Black (X)",
+        Position::Empty => "Nobody",
+    }
+}
+
+/// A line of play as its columns, such as `4 4 3 5`.
+fn pv_text(pv: &[CFMove]) -> String {
+    pv.iter()
+        .map(|action| (action.col + 1).to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 struct App<'a> {
     column_selection: u8,
     exit: bool,
     game: CFGameState,
+    /// The positions before each move played, for taking moves back.
+    history: Vec<CFGameState>,
+    player: Position,
+    mode: Mode,
     messages: VecDeque<Line<'a>>,
     app_state: AppState,
-    best_move: Option<BestResultHandle<CFGameState>>,
+    best_move: Option<SearchHandle<CFGameState>>,
+    hint: Option<SearchHandle<CFGameState>>,
+    /// How the AI and the hints search.
+    ai: AiConfig,
+    /// Visits the AI's trees kept from earlier searches of the current position.
+    inherited_visits: u64,
+    /// The AI's trees searching on during the human's turn.
+    ponder: Option<SearchHandle<CFGameState>>,
+    /// The evaluation bar, `None` while it is hidden.
+    evaluation: Option<Evaluation>,
+    /// Whether the expected line of play is previewed on the board as ghost discs.
+    show_pv: bool,
+    /// The position being set up, while editing.
+    editor: Option<Editor>,
+    /// Which part of the AI's trees to write to files.
+    trees: TreeOptions,
+    /// The tree searched for the last AI move, and the number of that move in the game.
+    last_tree: Option<(usize, TreeNode<CFGameState>)>,
 
     spinner_state: SpinnerState,
 }
@@ -42,7 +132,11 @@ impl<'a> Widget for &mut App<'a> {
 
         let horiz_layout = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints([Constraint::Length(2 * 7 + 1), Constraint::Min(0)])
+            .constraints([
+                Constraint::Length(2 * 7 + 1),
+                Constraint::Length(if self.evaluation.is_some() { 1 } else { 0 }),
+                Constraint::Min(0),
+            ])
             .margin(1)
             .spacing(2)
             .split(area);
@@ -74,48 +168,156 @@ impl<'a> Widget for &mut App<'a> {
 
         for x in 0..7 {
             for y in 0..6 {
-                use connect_four::Position::*;
                 let bg = Color::from_hsl(0.0, 0.0, 25.0);
-                let span = match self.game.pos(x, y) {
-                    Red => Span::from("O").style(Style::default().light_blue().bg(bg)),
-                    Black => Span::from("X").style(Style::default().light_red().bg(bg)),
-                    Empty => {
+                let cell = match &self.editor {
+                    Some(editor) => editor.pos(x, y),
+                    None => self.game.pos(x, y),
+                };
+                let span = match cell {
+                    connect_four::Position::Empty => {
                         Span::from(symbols::line::HORIZONTAL).style(Style::default().gray().bg(bg))
                     }
+                    player => {
+                        let (symbol, color) = disc_style(player);
+                        Span::from(symbol).style(Style::default().fg(color).bg(bg))
+                    }
                 };
 
                 buf.set_span(ix + 1 + (x * 2) as u16, iy + 1 + y as u16, &span, 1);
             }
         }
 
-        // determine last empty row
-        let max_row = {
-            let mut m = 5;
-            for y in 1..6 {
-                if self.game.pos(self.column_selection as usize, y) != connect_four::Position::Empty
-                {
-                    m = y - 1;
+        // the expected line of play, numbered in the order of its moves
+        if self.show_pv && !matches!(self.app_state, AppState::GameOver | AppState::Editing) {
+            let mut game = self.game.clone();
+            for (i, action) in self.pv().into_iter().take(PV_PREVIEW).enumerate() {
+                let Some(row) = game.landing_row(action.col) else {
                     break;
-                }
+                };
+                let bg = Color::from_hsl(0.0, 0.0, 25.0);
+                let span = Span::from((i + 1).to_string())
+                    .style(Style::default().fg(disc_style(action.color).1).bg(bg).dim());
+                buf.set_span(
+                    ix + 1 + (action.col * 2) as u16,
+                    iy + 1 + row as u16,
+                    &span,
+                    1,
+                );
+                game = game.apply_move(action);
             }
-            m as u16
-        };
-
-        for y in 0..=max_row {
-            let style = if y == max_row {
-                Style::default().black().on_white()
-            } else {
-                Style::default().black().on_yellow()
-            };
+        }
 
-            buf.set_line(
-                ix + 1 + 2 * self.column_selection as u16,
-                iy + 1 + y,
-                &Line::from(" ").style(style),
+        if let Some(editor) = &self.editor {
+            let (col, row) = editor.cursor();
+            let (symbol, color) = disc_style(editor.pos(col, row));
+            buf.set_span(
+                ix + 1 + (col * 2) as u16,
+                iy + 1 + row as u16,
+                &Span::from(symbol).style(Style::default().fg(color).on_white()),
                 1,
             );
         }
 
+        // the landing cell previews the disc of the player to move, unless the last move (such
+        // as one of the AI's in `watch`) filled the column
+        let landing_row = self
+            .game
+            .landing_row(self.column_selection as usize)
+            .filter(|_| self.editor.is_none());
+        if let Some(max_row) = landing_row {
+            let (symbol, color) = disc_style(self.game.next_player());
+            for y in 0..=max_row as u16 {
+                let line = if y == max_row as u16 {
+                    Line::from(symbol).style(Style::default().fg(color).on_white())
+                } else {
+                    Line::from(" ").style(Style::default().black().on_yellow())
+                };
+
+                buf.set_line(
+                    ix + 1 + 2 * self.column_selection as u16,
+                    iy + 1 + y,
+                    &line,
+                    1,
+                );
+            }
+        }
+
+        if let Some(evaluation) = &self.evaluation {
+            let (red, label) = evaluation.bar();
+            let bar_area = Rect {
+                height: board_layout[0].height,
+                ..horiz_layout[1]
+            };
+
+            // Red fills the bar from the bottom, Black from the top
+            let red_cells = (red * bar_area.height as f64).round() as u16;
+            for y in 0..bar_area.height {
+                let player = if y >= bar_area.height - red_cells {
+                    connect_four::Position::Red
+                } else {
+                    connect_four::Position::Black
+                };
+                buf.set_span(
+                    bar_area.x,
+                    bar_area.y + y,
+                    &Span::from(" ").bg(disc_style(player).1),
+                    1,
+                );
+            }
+
+            let label_area = Rect {
+                x: board_layout[1].x,
+                y: board_layout[1].y + 3,
+                width: horiz_layout[1].right() - board_layout[1].x,
+                height: 1,
+            };
+            Text::from(label)
+                .style(Style::default().gray())
+                .centered()
+                .render(label_area.intersection(area), buf);
+
+            let pv = evaluation.pv();
+            if !pv.is_empty() {
+                let pv_area = Rect {
+                    y: label_area.y + 1,
+                    ..label_area
+                };
+                Text::from(format!("pv {}", pv_text(&pv[..pv.len().min(7)])))
+                    .style(Style::default().gray())
+                    .centered()
+                    .render(pv_area.intersection(area), buf);
+            }
+        }
+
+        let mut messages_outer_area = horiz_layout[2];
+
+        let analysis = match &self.mode {
+            Mode::Watch(watch) => {
+                Some(watch.analysis(&self.game, self.app_state == AppState::GameOver))
+            }
+            Mode::Analysis(analysis) => Some(analysis.lines(&self.game)),
+            _ => None,
+        };
+        if let Some(analysis) = analysis {
+            let side_layout = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(analysis.len() as u16 + 1),
+                    Constraint::Min(0),
+                ])
+                .split(horiz_layout[2]);
+
+            let title = Title::from(" Analysis ".bold());
+            let analysis_area = Block::new()
+                .borders(Borders::TOP)
+                .title(title.alignment(Alignment::Center))
+                .border_set(symbols::border::PLAIN);
+
+            Paragraph::new(analysis).render(analysis_area.inner(side_layout[0]), buf);
+            analysis_area.render(side_layout[0], buf);
+            messages_outer_area = side_layout[1];
+        }
+
         let title = Title::from(" Messages ".bold());
 
         let messages_area = Block::new()
@@ -123,7 +325,7 @@ impl<'a> Widget for &mut App<'a> {
             .title(title.alignment(Alignment::Center))
             .border_set(symbols::border::PLAIN);
 
-        let messages_inner_area = messages_area.inner(horiz_layout[1]);
+        let messages_inner_area = messages_area.inner(messages_outer_area);
         let msgs = self
             .messages
             .iter()
@@ -134,11 +336,16 @@ impl<'a> Widget for &mut App<'a> {
         Paragraph::new(msgs)
             .wrap(Wrap { trim: true })
             .render(messages_inner_area, buf);
-        messages_area.render(horiz_layout[1], buf);
+        messages_area.render(messages_outer_area, buf);
 
         let selection_text_layout = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Max(1), Constraint::Min(0)])
+            .constraints([
+                Constraint::Max(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ])
             .split(board_layout[1]);
 
         Text::from(format!("column {}", self.column_selection + 1))
@@ -146,7 +353,16 @@ impl<'a> Widget for &mut App<'a> {
             .centered()
             .render(selection_text_layout[1], buf);
 
-        if self.app_state == AppState::AiThinking {
+        Text::from(self.status_line())
+            .centered()
+            .render(selection_text_layout[2], buf);
+
+        if self.hint.is_some()
+            || matches!(
+                self.app_state,
+                AppState::AiThinking | AppState::WaitingForOpponent
+            )
+        {
             let mut area = area;
             area.width = 25;
 
@@ -156,10 +372,19 @@ impl<'a> Widget for &mut App<'a> {
 }
 
 impl<'a> App<'a> {
-    fn new(game: CFGameState) -> Self {
+    fn new(game: CFGameState, mode: Mode, ai: AiConfig, trees: TreeOptions) -> Self {
         let mut messages = VecDeque::new();
 
-        let msg_play = "Use the arrow keys to choose where to play. Then press enter or spacebar.";
+        let msg_play = match mode {
+            Mode::Watch(_) => {
+                "Press p to pause or resume, and s to play a single move while paused."
+            }
+            Mode::Analysis(_) => {
+                "Play moves for either side with the arrow keys and enter, while the engine \
+                 searches the position."
+            }
+            _ => "Use the arrow keys to choose where to play. Then press enter or spacebar.",
+        };
         let line = Line::from(msg_play).style(Style::default().light_green());
         messages.push_front(line);
 
@@ -167,15 +392,102 @@ impl<'a> App<'a> {
         let line = Line::from(msg).style(Style::default().red());
         messages.push_front(line);
 
-        Self {
+        let (player, app_state) = match &mode {
+            Mode::Remote(connection) => {
+                let line = Line::from("Press r to resign.").style(Style::default().light_green());
+                messages.push_front(line);
+
+                if let Ok(addr) = connection.peer_addr() {
+                    let line = Line::from(format!("Connected to {addr}."))
+                        .style(Style::default().light_green());
+                    messages.push_front(line);
+                }
+
+                if connection.color == game.next_player() {
+                    (connection.color, AppState::ColumnSelection)
+                } else {
+                    let line = Line::from("Waiting for the opponent to move.")
+                        .style(Style::default().light_red());
+                    messages.push_front(line);
+                    (connection.color, AppState::WaitingForOpponent)
+                }
+            }
+            Mode::Analysis(_) if game.is_terminal_state().is_some() => {
+                (game.next_player(), AppState::GameOver)
+            }
+            Mode::VsAi | Mode::HotSeat | Mode::Analysis(_) => {
+                (game.next_player(), AppState::ColumnSelection)
+            }
+            Mode::Watch(_) => (game.next_player(), AppState::AiThinking),
+        };
+
+        // a hint would be the AI playing for a remote player, and the analysis panel shows more
+        if app_state != AppState::AiThinking && matches!(mode, Mode::VsAi | Mode::HotSeat) {
+            let line =
+                Line::from("Press h for a hint from the AI.").style(Style::default().light_green());
+            messages.push_front(line);
+        }
+
+        let msg_edit = match mode {
+            Mode::VsAi => {
+                Some("Press u to take back a move, n for a new game and m to make the AI move now.")
+            }
+            Mode::HotSeat => Some("Press u to take back a move and n for a new game."),
+            Mode::Analysis(_) => Some(
+                "Press u to take back a move, n for a new game and m to play the engine's move.",
+            ),
+            Mode::Remote(_) | Mode::Watch(_) => None,
+        };
+        if let Some(msg) = msg_edit {
+            messages.push_front(Line::from(msg).style(Style::default().light_green()));
+            let line = Line::from("Press b to set up a position in the board editor.")
+                .style(Style::default().light_green());
+            messages.push_front(line);
+        }
+
+        if matches!(mode, Mode::VsAi | Mode::Watch(_)) {
+            let line = Line::from("Press t to write the tree of the last AI move to files.")
+                .style(Style::default().light_green());
+            messages.push_front(line);
+        }
+
+        // the evaluation would help the player against a remote opponent
+        if !matches!(mode, Mode::Remote(_)) {
+            let line = Line::from(
+                "Press e to show or hide the evaluation and v to preview the expected line of play.",
+            )
+            .style(Style::default().light_green());
+            messages.push_front(line);
+        }
+
+        let mut app = Self {
             column_selection: 0,
             exit: false,
+            // the analysis panel already shows what the evaluation would, and in a hot seat game
+            // it is up to the players
+            evaluation: matches!(mode, Mode::VsAi | Mode::Watch(_)).then(|| Evaluation::new(&game)),
+            show_pv: false,
+            editor: None,
             game,
+            history: Vec::new(),
+            player,
+            mode,
             messages,
             best_move: None,
-            app_state: AppState::ColumnSelection,
+            hint: None,
+            ai,
+            inherited_visits: 0,
+            ponder: None,
+            trees,
+            last_tree: None,
+            app_state,
             spinner_state: SpinnerState::new(15),
+        };
+        // a position given to start from may have the first column full
+        if app.app_state == AppState::ColumnSelection {
+            app.maybe_move_column_selection();
         }
+        app
     }
 
     fn run(&mut self, terminal: &mut tui::Tui) -> io::Result<()> {
@@ -206,50 +518,454 @@ impl<'a> App<'a> {
         }
 
         // check if done processing
-        let best_move_handle = self.best_move.take();
-        if best_move_handle.is_some() {
-            let mut best_move_handle = best_move_handle.unwrap();
+        if let Some(best_move_handle) = self.best_move.take() {
             if best_move_handle.is_finished() {
-                let result = best_move_handle.join();
+                self.play_ai_move(best_move_handle);
+            } else {
+                self.best_move = Some(best_move_handle);
+            }
+        }
 
-                let line = Line::from(format!(
-                    "AI plays to column {} after thinking for {} moves.",
-                    result.best_move.col + 1,
-                    result.iterations
-                ))
-                .style(Style::default().light_red());
+        if let Some(hint_handle) = self.hint.take() {
+            if hint_handle.is_finished() {
+                let best = hint_handle.stop().select(self.ai.selection);
+                if let (Some(best), AppState::ColumnSelection) = (best, self.app_state) {
+                    let best_move = best.action;
+                    let msg = if best.proven == Some(Outcome::Win) {
+                        format!("Hint: column {} wins by force.", best_move.col + 1)
+                    } else {
+                        format!("Hint: play column {}.", best_move.col + 1)
+                    };
+                    let line = Line::from(msg).style(Style::default().light_green());
+                    self.messages.push_front(line);
+                    self.column_selection = best_move.col as u8;
+                }
+            } else {
+                self.hint = Some(hint_handle);
+            }
+        }
+
+        // check for moves from a remote opponent
+        let event = match &self.mode {
+            Mode::Remote(connection) => connection.try_recv(),
+            Mode::VsAi | Mode::HotSeat | Mode::Watch(_) | Mode::Analysis(_) => None,
+        };
+        if let Some(event) = event {
+            self.handle_net_event(event);
+        }
+
+        self.advance_watch();
+
+        if let Mode::Analysis(analysis) = &mut self.mode {
+            analysis.poll(&self.game);
+        }
+
+        if let Some(evaluation) = &mut self.evaluation {
+            evaluation.poll(&self.game);
+        }
+
+        Ok(())
+    }
+
+    /// Plays a move and remembers the position before it.
+    fn play_move(&mut self, action: CFMove) {
+        self.history.push(self.game.clone());
+        self.game = self.game.apply_move(action);
+    }
+
+    /// Stops the AI's search, whether or not it has used up its time, and plays its best move.
+    fn play_ai_move(&mut self, handle: SearchHandle<CFGameState>) {
+        let mut searches = handle.into_searches();
+        let snapshots = searches.iter().map(Search::snapshot).collect::<Vec<_>>();
+        let snapshot = Snapshot::merge(&snapshots);
+        let (best_move, proven) = match snapshot.select(self.ai.selection) {
+            Some(best) => (best.action, best.proven),
+            // only a search that never ran has no move to offer
+            None => (self.game.all_moves()[0], None),
+        };
+
+        let mut msg = format!(
+            "AI plays to column {} after thinking for {} moves, reusing {} visits.",
+            best_move.col + 1,
+            snapshot.iterations,
+            self.inherited_visits
+        );
+        if proven == Some(Outcome::Win) {
+            msg += " Forced win found!";
+        }
+        let line = Line::from(msg).style(Style::default().light_red());
+        self.messages.push_front(line);
+
+        let tree = self.trees.tree(&searches);
+        self.keep_tree(tree);
+        self.play_move(best_move);
+        for search in &mut searches {
+            search.advance(best_move);
+        }
+
+        if !self.check_game_over() {
+            self.app_state = AppState::ColumnSelection;
+            self.ponder = Some(SearchHandle::spawn_parallel(searches, self.ponder_budget()));
+        }
+    }
+
+    /// Remembers the tree searched for the AI move about to be played, and writes it to the dump
+    /// directory if there is one.
+    fn keep_tree(&mut self, tree: Option<TreeNode<CFGameState>>) {
+        let Some(tree) = tree else {
+            return;
+        };
+        let number = self.history.len() + 1;
+        if self.trees.dump_trees.is_some() {
+            if let Err(err) = self.trees.write(&tree, number) {
+                let line = Line::from(format!("Could not write the tree: {err}"))
+                    .style(Style::default().light_red());
                 self.messages.push_front(line);
+            }
+        }
+        self.last_tree = Some((number, tree));
+    }
 
-                self.game = self.game.apply_move(result.best_move);
+    /// Writes the tree of the last AI move to files.
+    fn write_last_tree(&mut self) {
+        let msg = match &self.last_tree {
+            None => "The AI has not moved yet.".to_string(),
+            Some((number, tree)) => match self.trees.write(tree, *number) {
+                Ok([dot, json]) => format!(
+                    "Wrote the tree of move {number} to {} and {}.",
+                    dot.display(),
+                    json.display()
+                ),
+                Err(err) => format!("Could not write the tree: {err}"),
+            },
+        };
+        self.messages
+            .push_front(Line::from(msg).style(Style::default().light_green()));
+    }
 
-                if let Some(win) = self.game.is_terminal_state() {
-                    if win == Position::Black {
-                        let line = Line::from("AI Wins!").style(Style::default().light_red());
-                        self.messages.push_front(line);
-                    } else {
-                        let line = Line::from("Tie").style(Style::default().light_blue());
-                        self.messages.push_front(line);
-                    }
+    /// Stops every search of the AI and waits for their threads.
+    fn cancel_searches(&mut self) {
+        for handle in [self.best_move.take(), self.hint.take(), self.ponder.take()]
+            .into_iter()
+            .flatten()
+        {
+            handle.into_searches();
+        }
+    }
 
-                    self.app_state = AppState::GameOver;
-                } else {
-                    self.app_state = AppState::ColumnSelection;
+    /// Takes back the last move, or against the AI the last move of the human and the AI's
+    /// reply.
+    fn undo(&mut self) {
+        let human = self.player;
+        let Some(index) = self
+            .history
+            .iter()
+            .rposition(|state| !matches!(self.mode, Mode::VsAi) || state.next_player() == human)
+        else {
+            let line = Line::from("Nothing to take back.").style(Style::default().light_red());
+            self.messages.push_front(line);
+            return;
+        };
+
+        self.cancel_searches();
+        let taken = self.history.len() - index;
+        self.game = self.history[index].clone();
+        self.history.truncate(index);
+
+        if let Mode::HotSeat | Mode::Analysis(_) = self.mode {
+            self.player = self.game.next_player();
+        }
+        self.app_state = AppState::ColumnSelection;
+        self.maybe_move_column_selection();
+
+        let moves = if taken == 1 { "move" } else { "moves" };
+        let line =
+            Line::from(format!("Took back {taken} {moves}.")).style(Style::default().light_green());
+        self.messages.push_front(line);
+    }
+
+    fn new_game(&mut self) {
+        self.cancel_searches();
+        self.game = CFGameState::default();
+        self.history.clear();
+        self.player = self.game.next_player();
+        self.app_state = AppState::ColumnSelection;
+        self.column_selection = 0;
+
+        let line = Line::from("New game.").style(Style::default().light_green());
+        self.messages.push_front(line);
+    }
+
+    fn advance_watch(&mut self) {
+        let Mode::Watch(watch) = &mut self.mode else {
+            return;
+        };
+        if self.app_state == AppState::GameOver {
+            return;
+        }
+
+        if let Some(action) = watch.poll(&self.game) {
+            let tree = self.trees.tree(watch.searched());
+            let line = Line::from(format!(
+                "{} plays to column {} after thinking for {} moves.",
+                player_name(action.color),
+                action.col + 1,
+                watch.iterations(action.color)
+            ))
+            .fg(disc_style(action.color).1);
+            self.messages.push_front(line);
+
+            self.keep_tree(tree);
+            self.play_move(action);
+            self.column_selection = action.col as u8;
+            self.check_game_over();
+        }
+    }
+
+    fn handle_net_event(&mut self, event: NetEvent) {
+        if self.app_state == AppState::GameOver {
+            return;
+        }
+
+        let reason = match event {
+            NetEvent::Message(Message::Move { col })
+                if self.app_state == AppState::WaitingForOpponent =>
+            {
+                if self.game.can_play(col) {
+                    let line = Line::from(format!("Opponent plays to column {}", col + 1))
+                        .style(Style::default().light_red());
+                    self.messages.push_front(line);
+
+                    let color = self.game.next_player();
+                    self.play_move(CFMove { color, col });
+
+                    if !self.check_game_over() {
+                        self.maybe_move_column_selection();
+                        self.app_state = AppState::ColumnSelection;
+                    }
+                    return;
                 }
-            } else {
-                self.best_move = Some(best_move_handle);
+
+                self.send_to_opponent(&Message::Error("illegal move".into()));
+                format!("Opponent tried the illegal move {}.", col + 1)
+            }
+            NetEvent::Message(Message::Resign) => "Opponent resigned. You win!".to_string(),
+            NetEvent::Message(Message::Bye) | NetEvent::Disconnected => {
+                "Opponent disconnected.".to_string()
+            }
+            NetEvent::Message(Message::Error(reason)) => format!("Opponent reported: {reason}"),
+            NetEvent::Message(msg) => format!("Unexpected message from opponent: {msg}"),
+            NetEvent::Invalid(line) => format!("Unexpected message from opponent: {line}"),
+        };
+
+        let line = Line::from(reason).style(Style::default().light_red());
+        self.messages.push_front(line);
+        self.app_state = AppState::GameOver;
+    }
+
+    fn send_to_opponent(&mut self, msg: &Message) {
+        if let Mode::Remote(connection) = &mut self.mode {
+            if connection.send(msg).is_err() {
+                let line =
+                    Line::from("Lost connection to opponent.").style(Style::default().light_red());
+                self.messages.push_front(line);
+                self.app_state = AppState::GameOver;
             }
         }
+    }
 
-        Ok(())
+    /// Reports the result and switches to `GameOver` if the game has ended.
+    fn check_game_over(&mut self) -> bool {
+        let Some(win) = self.game.is_terminal_state() else {
+            return false;
+        };
+
+        let line = if win == Position::Empty {
+            Line::from("Tie").style(Style::default().light_blue())
+        } else if matches!(
+            self.mode,
+            Mode::HotSeat | Mode::Watch(_) | Mode::Analysis(_)
+        ) {
+            Line::from(format!("{} wins!", player_name(win))).fg(disc_style(win).1)
+        } else if win == self.player {
+            Line::from("You win!").style(Style::default().light_blue())
+        } else if let Mode::VsAi = self.mode {
+            Line::from("AI Wins!").style(Style::default().light_red())
+        } else {
+            Line::from("Opponent wins!").style(Style::default().light_red())
+        };
+        self.messages.push_front(line);
+
+        self.app_state = AppState::GameOver;
+        true
+    }
+
+    /// The expected line of play from the current position: that of the AI to move when watching,
+    /// and otherwise that of the evaluation.
+    fn pv(&self) -> Vec<CFMove> {
+        match (&self.mode, &self.evaluation) {
+            (Mode::Watch(watch), _) => watch.pv(&self.game),
+            (Mode::Analysis(analysis), _) => analysis.pv(&self.game),
+            (_, Some(evaluation)) => evaluation.pv(),
+            (_, None) => Vec::new(),
+        }
+    }
+
+    /// Searches the current position on every thread.
+    fn think(&self) -> SearchHandle<CFGameState> {
+        SearchHandle::spawn_parallel(self.ai.searches(&self.game), self.ai.budget)
+    }
+
+    /// How long to ponder. How far pondering gets depends on how long the human takes, so the AI
+    /// does not ponder when it is meant to play the same moves on every run.
+    fn ponder_budget(&self) -> Budget {
+        match self.ai.budget {
+            Budget::Time(_) | Budget::Infinite => Budget::Time(PONDER_TIME),
+            Budget::Iterations(_) => Budget::Iterations(0),
+        }
+    }
+
+    /// Starts the AI's move, continuing the trees it has been pondering since its last move from
+    /// the human's reply.
+    fn think_about_move(&mut self, reply: CFMove) -> SearchHandle<CFGameState> {
+        let mut searches = match self.ponder.take() {
+            Some(ponder) => ponder.into_searches(),
+            None => Vec::new(),
+        };
+        self.inherited_visits = searches.iter_mut().map(|s| s.advance(reply)).sum();
+        if searches.is_empty() {
+            searches = self.ai.searches(&self.game);
+        }
+        SearchHandle::spawn_parallel(searches, self.ai.budget)
+    }
+
+    /// A hint that needs no search: an immediate win, or the opponent's immediate win to block.
+    fn tactical_hint(&self) -> Option<(usize, String)> {
+        let player = self.game.next_player();
+        if let Some(&col) = self.game.winning_cols(player).first() {
+            return Some((col, format!("Hint: column {} wins right away.", col + 1)));
+        }
+        let threats = self.game.winning_cols(player.opponent());
+        match threats.as_slice() {
+            [] => None,
+            [col] => Some((*col, format!("Hint: block column {}.", col + 1))),
+            [col, ..] => Some((
+                *col,
+                "Hint: the opponent has more than one winning move.".to_string(),
+            )),
+        }
+    }
+
+    fn status_line(&self) -> Line<'static> {
+        match self.app_state {
+            AppState::ColumnSelection if matches!(self.mode, Mode::HotSeat | Mode::Analysis(_)) => {
+                let player = self.game.next_player();
+                Line::from(format!("{} to move", player_name(player))).fg(disc_style(player).1)
+            }
+            AppState::ColumnSelection => Line::from("Your move").light_blue(),
+            AppState::AiThinking => match &self.mode {
+                Mode::Watch(watch) if watch.is_paused() => Line::from("Paused").gray(),
+                Mode::Watch(_) => {
+                    let player = self.game.next_player();
+                    Line::from(format!("{} is thinking", player_name(player)))
+                        .fg(disc_style(player).1)
+                }
+                _ => Line::from("AI is thinking").light_red(),
+            },
+            AppState::WaitingForOpponent => Line::from("Opponent's move").light_red(),
+            AppState::Editing => {
+                let player = self
+                    .editor
+                    .as_ref()
+                    .map_or(Position::Red, Editor::next_player);
+                Line::from(format!("Editing, {} to move", player_name(player)))
+                    .fg(disc_style(player).1)
+            }
+            AppState::GameOver => Line::from("Game over").gray(),
+        }
     }
 
     fn handle_key_event(&mut self, key: KeyEvent, state: AppState) {
         use ratatui::crossterm::event::KeyCode::*;
+        if state == AppState::Editing {
+            self.handle_editor_key(key);
+            return;
+        }
+
         match key.code {
             Char('q') | Esc => {
+                self.cancel_searches();
                 self.exit = true;
                 return;
             }
+            Char('m') if state == AppState::AiThinking && matches!(self.mode, Mode::VsAi) => {
+                if let Some(handle) = self.best_move.take() {
+                    self.play_ai_move(handle);
+                }
+                return;
+            }
+            Char('m')
+                if state == AppState::ColumnSelection && matches!(self.mode, Mode::Analysis(_)) =>
+            {
+                let best = match &self.mode {
+                    Mode::Analysis(analysis) => analysis.best(&self.game),
+                    _ => None,
+                };
+                if let Some(best) = best {
+                    self.column_selection = best.col as u8;
+                    self.play_selected_column();
+                }
+                return;
+            }
+            Char('u') if matches!(self.mode, Mode::VsAi | Mode::HotSeat | Mode::Analysis(_)) => {
+                self.undo();
+                return;
+            }
+            Char('n') if matches!(self.mode, Mode::VsAi | Mode::HotSeat | Mode::Analysis(_)) => {
+                self.new_game();
+                return;
+            }
+            Char('b')
+                if matches!(self.mode, Mode::VsAi | Mode::HotSeat | Mode::Analysis(_))
+                    && matches!(state, AppState::ColumnSelection | AppState::GameOver) =>
+            {
+                self.start_editing();
+                return;
+            }
+            Char('t') if matches!(self.mode, Mode::VsAi | Mode::Watch(_)) => {
+                self.write_last_tree();
+                return;
+            }
+            Char('v') if !matches!(self.mode, Mode::Remote(_)) => {
+                self.show_pv = !self.show_pv;
+                return;
+            }
+            Char('e') if !matches!(self.mode, Mode::Remote(_)) => {
+                self.evaluation = match self.evaluation {
+                    Some(_) => None,
+                    None => Some(Evaluation::new(&self.game)),
+                };
+                return;
+            }
+            Char('r') if matches!(self.mode, Mode::Remote(_)) && state != AppState::GameOver => {
+                self.send_to_opponent(&Message::Resign);
+                let line = Line::from("You resigned.").style(Style::default().light_red());
+                self.messages.push_front(line);
+                self.app_state = AppState::GameOver;
+                return;
+            }
+            Char('p') | Char(' ') if matches!(self.mode, Mode::Watch(_)) => {
+                if let Mode::Watch(watch) = &mut self.mode {
+                    watch.toggle_pause();
+                }
+                return;
+            }
+            Char('s') if matches!(self.mode, Mode::Watch(_)) => {
+                if let Mode::Watch(watch) = &mut self.mode {
+                    watch.step();
+                }
+                return;
+            }
             _ => {}
         };
 
@@ -257,45 +973,148 @@ impl<'a> App<'a> {
             match key.code {
                 Left => self.move_left(),
                 Right => self.move_right(),
-                Enter | Char(' ') => {
-                    let player_move = CFMove {
-                        color: Position::Red,
-                        col: self.column_selection as usize,
-                    };
-                    self.game = self.game.apply_move(player_move);
-
-                    let line =
-                        Line::from(format!("Playing to column {}", self.column_selection + 1))
-                            .style(Style::default().light_blue());
+                Char('h')
+                    if self.hint.is_none() && matches!(self.mode, Mode::VsAi | Mode::HotSeat) =>
+                {
+                    if let Some((col, hint)) = self.tactical_hint() {
+                        self.messages
+                            .push_front(Line::from(hint).style(Style::default().light_green()));
+                        self.column_selection = col as u8;
+                        return;
+                    }
 
+                    let line = Line::from("Thinking about a hint...")
+                        .style(Style::default().light_green());
                     self.messages.push_front(line);
 
-                    if let Some(win) = self.game.is_terminal_state() {
-                        if win == Position::Red {
-                            let line = Line::from("You win!").style(Style::default().light_blue());
-                            self.messages.push_front(line);
-                        } else {
-                            let line = Line::from("Tie").style(Style::default().light_blue());
-                            self.messages.push_front(line);
-                        }
+                    self.hint = Some(self.think());
+                }
+                Enter | Char(' ') => self.play_selected_column(),
+                _ => {}
+            }
+        }
+    }
 
-                        self.app_state = AppState::GameOver;
-                    } else {
-                        let mcts = yamcts::MCTS::<DefaultRng>::default();
+    /// Plays the human's move in the selected column, and hands the turn to whoever is next.
+    fn play_selected_column(&mut self) {
+        if let Some(hint) = self.hint.take() {
+            hint.into_searches();
+        }
+        let player_move = CFMove {
+            color: self.game.next_player(),
+            col: self.column_selection as usize,
+        };
+        self.play_move(player_move);
 
-                        self.best_move =
-                            Some(mcts.run_with_duration(
-                                self.game.clone(),
-                                chrono::TimeDelta::seconds(1),
-                            ));
+        let line = if let Mode::HotSeat | Mode::Analysis(_) = self.mode {
+            Line::from(format!(
+                "{} plays to column {}",
+                player_name(player_move.color),
+                self.column_selection + 1
+            ))
+            .fg(disc_style(player_move.color).1)
+        } else {
+            Line::from(format!("Playing to column {}", self.column_selection + 1))
+                .style(Style::default().light_blue())
+        };
 
-                        self.maybe_move_column_selection();
+        self.messages.push_front(line);
 
-                        self.app_state = AppState::AiThinking;
-                        self.spinner_state = SpinnerState::new(15);
-                    }
+        if self.check_game_over() {
+            return;
+        }
+
+        if let Mode::HotSeat | Mode::Analysis(_) = self.mode {
+            self.player = self.game.next_player();
+            self.maybe_move_column_selection();
+        } else if let Mode::Remote(_) = self.mode {
+            let col = self.column_selection as usize;
+            self.maybe_move_column_selection();
+            self.app_state = AppState::WaitingForOpponent;
+            self.spinner_state = SpinnerState::new(15);
+            self.send_to_opponent(&Message::Move { col });
+        } else {
+            self.best_move = Some(self.think_about_move(player_move));
+
+            self.maybe_move_column_selection();
+
+            self.app_state = AppState::AiThinking;
+            self.spinner_state = SpinnerState::new(15);
+        }
+    }
+
+    fn start_editing(&mut self) {
+        self.cancel_searches();
+        self.editor = Some(Editor::new(&self.game, self.column_selection as usize));
+        self.app_state = AppState::Editing;
+
+        let line = Line::from(
+            "Board editor: o and x place discs, space empties a cell and c clears the board. Enter \
+             plays the position, Escape leaves it as it was.",
+        )
+        .style(Style::default().light_green());
+        self.messages.push_front(line);
+    }
+
+    fn handle_editor_key(&mut self, key: KeyEvent) {
+        use ratatui::crossterm::event::KeyCode::*;
+        let Some(editor) = &mut self.editor else {
+            return;
+        };
+        match key.code {
+            Left => editor.move_cursor(-1, 0),
+            Right => editor.move_cursor(1, 0),
+            Up => editor.move_cursor(0, -1),
+            Down => editor.move_cursor(0, 1),
+            Char('o') => editor.set(Position::Red),
+            Char('x') => editor.set(Position::Black),
+            Char(' ') | Backspace | Delete => editor.set(Position::Empty),
+            Char('c') => editor.clear(),
+            Enter => self.finish_editing(),
+            Esc => {
+                self.editor = None;
+                self.app_state = if self.game.is_terminal_state().is_some() {
+                    AppState::GameOver
+                } else {
+                    AppState::ColumnSelection
+                };
+                let line =
+                    Line::from("Left the board as it was.").style(Style::default().light_green());
+                self.messages.push_front(line);
+            }
+            _ => {}
+        }
+    }
+
+    /// Plays on from the position set up in the editor, if it could have been reached by
+    /// playing. Against the AI the human takes the side to move.
+    fn finish_editing(&mut self) {
+        let Some(editor) = &self.editor else {
+            return;
+        };
+        match editor.finish() {
+            Ok(game) => {
+                self.editor = None;
+                self.game = game;
+                self.history.clear();
+                self.player = self.game.next_player();
+
+                let line = Line::from(format!(
+                    "Position set up, {} to move.",
+                    player_name(self.player)
+                ))
+                .style(Style::default().light_green());
+                self.messages.push_front(line);
+
+                if !self.check_game_over() {
+                    self.app_state = AppState::ColumnSelection;
+                    self.maybe_move_column_selection();
                 }
-                _ => {}
+            }
+            Err(err) => {
+                let line = Line::from(format!("This position cannot be played: {err}."))
+                    .style(Style::default().light_red());
+                self.messages.push_front(line);
             }
         }
     }
@@ -362,13 +1181,223 @@ impl<'a> App<'a> {
     }
 }
 
+/// Connect Four with a Monte Carlo Tree Search AI.
+#[derive(Parser)]
+#[command(version, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    ai: AiArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Play against the AI in the terminal (the default)
+    Play {
+        /// Two players take turns at the same keyboard instead
+        #[arg(long)]
+        hot_seat: bool,
+        #[command(flatten)]
+        trees: TreeOptions,
+        #[command(flatten)]
+        ai: AiArgs,
+    },
+    /// Speak the line-based engine protocol on stdin/stdout
+    Engine,
+    /// Serve a JSON API for the engine over HTTP on localhost
+    #[cfg(feature = "server")]
+    Serve {
+        #[arg(long, default_value_t = 7878)]
+        port: u16,
+    },
+    /// Host a game against another player over TCP
+    Host {
+        #[arg(long, default_value = "0.0.0.0:7879")]
+        addr: String,
+    },
+    /// Join a game hosted by another player
+    Join { addr: String },
+    /// Play moves for either side while the engine searches the position, like an analysis
+    /// board
+    Analyse {
+        /// Starts from the position after these moves, such as 4453
+        moves: Option<String>,
+        #[command(flatten)]
+        ai: AiArgs,
+    },
+    /// Watch two AI players play against each other
+    Watch {
+        /// Thinking time per move for Red, in milliseconds
+        #[arg(long, default_value_t = 1000)]
+        red_time: u64,
+        /// Thinking time per move for Black, in milliseconds
+        #[arg(long, default_value_t = 1000)]
+        black_time: u64,
+        /// UCT exploration constant for Red, --exploration by default
+        #[arg(long, value_parser = config::parse_exploration)]
+        red_exploration: Option<f64>,
+        /// UCT exploration constant for Black, --exploration by default
+        #[arg(long, value_parser = config::parse_exploration)]
+        black_exploration: Option<f64>,
+        /// Least time between two moves, in milliseconds
+        #[arg(long, default_value_t = 500)]
+        delay: u64,
+        #[command(flatten)]
+        trees: TreeOptions,
+        #[command(flatten)]
+        ai: AiArgs,
+    },
+}
+
 fn main() -> io::Result<()> {
     env_logger::init();
 
+    let cli = Cli::parse();
+    let command = cli.command.unwrap_or(Command::Play {
+        hot_seat: false,
+        trees: TreeOptions::default(),
+        ai: cli.ai,
+    });
+    match command {
+        Command::Play {
+            hot_seat: false,
+            trees,
+            ai,
+        } => {
+            let ai = ai_config(&settings(ai), THINK_TIME, None);
+            play(CFGameState::default(), Mode::VsAi, ai, trees)
+        }
+        Command::Play {
+            hot_seat: true,
+            trees,
+            ai,
+        } => {
+            if trees.dump_trees.is_some() {
+                Cli::command()
+                    .error(
+                        ErrorKind::ArgumentConflict,
+                        "--dump-trees needs an AI player, which a hot seat game does not have",
+                    )
+                    .exit();
+            }
+            let ai = ai_config(&settings(ai), THINK_TIME, None);
+            play(CFGameState::default(), Mode::HotSeat, ai, trees)
+        }
+        Command::Engine => engine::run(io::stdin().lock(), io::stdout()),
+        #[cfg(feature = "server")]
+        Command::Serve { port } => {
+            connect_four_mcts::server::Server::bind(("127.0.0.1", port))?.run()
+        }
+        Command::Host { addr } => {
+            let host = net::Host::bind(addr)?;
+            println!(
+                "Waiting for an opponent to join on {}...",
+                host.local_addr()?
+            );
+            play_remote(Mode::Remote(host.accept()?))
+        }
+        Command::Join { addr } => play_remote(Mode::Remote(net::join(addr)?)),
+        Command::Analyse { moves, ai } => {
+            let game = match moves.as_deref().map(notation::parse) {
+                None => CFGameState::default(),
+                Some(Ok(game)) => game,
+                Some(Err(err)) => Cli::command()
+                    .error(ErrorKind::InvalidValue, format!("invalid moves: {err}"))
+                    .exit(),
+            };
+            let ai = ai_config(&settings(ai), THINK_TIME, None);
+            let analysis = Analysis::new(ai);
+            play(
+                game,
+                Mode::Analysis(Box::new(analysis)),
+                ai,
+                TreeOptions::default(),
+            )
+        }
+        Command::Watch {
+            red_time,
+            black_time,
+            red_exploration,
+            black_exploration,
+            delay,
+            trees,
+            ai,
+        } => {
+            let settings = settings(ai);
+            let red = ai_config(&settings, Duration::from_millis(red_time), red_exploration);
+            let black = ai_config(
+                &settings,
+                Duration::from_millis(black_time),
+                black_exploration,
+            );
+            let watch = Watch::new(red, black, Duration::from_millis(delay));
+            let ai = ai_config(&settings, THINK_TIME, None);
+            play(
+                CFGameState::default(),
+                Mode::Watch(Box::new(watch)),
+                ai,
+                trees,
+            )
+        }
+    }
+}
+
+/// Reads the AI settings of a mode, exiting with a usage error if they are invalid.
+fn settings(ai: AiArgs) -> Settings {
+    let settings = ai
+        .settings()
+        .unwrap_or_else(|err| Cli::command().error(ErrorKind::Io, err).exit());
+    if settings.seed.is_some() && settings.iterations.is_none() {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "--seed needs --iterations, as a thinking time depends on the speed of the machine",
+            )
+            .exit();
+    }
+    settings
+}
+
+/// An AI thinking for `think_time` unless the settings give it a number of iterations, with
+/// `exploration` in place of the one in the settings.
+fn ai_config(settings: &Settings, think_time: Duration, exploration: Option<f64>) -> AiConfig {
+    AiConfig {
+        budget: settings
+            .iterations
+            .map_or(Budget::Time(think_time), Budget::Iterations),
+        selection: settings.selection.unwrap_or_default(),
+        // the moves of a seeded AI depend on the number of threads, which must not be that of
+        // the machine it happens to run on
+        threads: settings
+            .threads
+            .unwrap_or_else(|| match settings.seed {
+                Some(_) => 1,
+                None => search::default_threads(),
+            })
+            .max(1),
+        search: SearchConfig {
+            exploration_factor: exploration
+                .or(settings.exploration)
+                .unwrap_or(std::f64::consts::SQRT_2),
+            draw_value: settings.draw_value.unwrap_or(0.0),
+            rave: settings.rave,
+            seed: settings.seed,
+        },
+    }
+}
+
+/// Plays against a remote opponent, which needs no AI settings.
+fn play_remote(mode: Mode) -> io::Result<()> {
+    let ai = ai_config(&Settings::default(), THINK_TIME, None);
+    play(CFGameState::default(), mode, ai, TreeOptions::default())
+}
+
+fn play(game: CFGameState, mode: Mode, ai: AiConfig, trees: TreeOptions) -> io::Result<()> {
     tui::init_panic_hook();
     let mut terminal = tui::init()?;
 
-    App::new(CFGameState::new(Position::Red, Position::Black)).run(&mut terminal)?;
+    App::new(game, mode, ai, trees).run(&mut terminal)?;
 
     tui::restore()?;
 
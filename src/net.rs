@@ -0,0 +1,347 @@
+//! Two-player games over TCP.
+//!
+//! Both sides exchange newline terminated text messages. The joining side opens with
+//! `hello <version>`, the host answers `welcome <version> <color>` with the color assigned to the
+//! joiner, or `error <reason>` before closing the connection. After that each side sends
+//! `move <col>` (1 to 7), `resign` or `bye` when leaving. Moves are validated by the receiver
+//! as well as the sender.
+
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::Duration,
+};
+
+use log::debug;
+
+use crate::connect_four::Position;
+
+/// The version sent in `hello` and `welcome`, both sides must agree on it.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A single line of the protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum Message {
+    Hello {
+        version: u32,
+    },
+    /// `color` is the color assigned to the joining side.
+    Welcome {
+        version: u32,
+        color: Position,
+    },
+    /// `col` is counted from 0, but sent counted from 1.
+    Move {
+        col: usize,
+    },
+    Resign,
+    Bye,
+    Error(String),
+}
+
+impl Message {
+    /// Parses one line, returning `None` if it is not part of the protocol.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut tokens = line.split_whitespace();
+        let msg = match tokens.next()? {
+            "hello" => Message::Hello {
+                version: tokens.next()?.parse().ok()?,
+            },
+            "welcome" => Message::Welcome {
+                version: tokens.next()?.parse().ok()?,
+                color: match tokens.next()? {
+                    "red" => Position::Red,
+                    "black" => Position::Black,
+                    _ => return None,
+                },
+            },
+            "move" => Message::Move {
+                col: tokens
+                    .next()?
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|col| (1..=7).contains(col))?
+                    - 1,
+            },
+            "resign" => Message::Resign,
+            "bye" => Message::Bye,
+            "error" => Message::Error(tokens.collect::<Vec<_>>().join(" ")),
+            _ => return None,
+        };
+        Some(msg)
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Message::Hello { version } => write!(f, "hello {version}"),
+            Message::Welcome { version, color } => {
+                let color = if *color == Position::Red {
+                    "red"
+                } else {
+                    "black"
+                };
+                write!(f, "welcome {version} {color}")
+            }
+            Message::Move { col } => write!(f, "move {}", col + 1),
+            Message::Resign => write!(f, "resign"),
+            Message::Bye => write!(f, "bye"),
+            Message::Error(reason) => write!(f, "error {reason}"),
+        }
+    }
+}
+
+/// Something that happened on the other side of a [`Connection`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum NetEvent {
+    /// A message was received.
+    Message(Message),
+    /// The peer sent something that is not part of the protocol.
+    Invalid(String),
+    /// The connection was closed.
+    Disconnected,
+}
+
+/// An established connection to the other player.
+pub struct Connection {
+    stream: TcpStream,
+    events: Receiver<NetEvent>,
+    /// The color played on this side of the connection.
+    pub color: Position,
+}
+
+impl Connection {
+    fn start(stream: TcpStream, reader: BufReader<TcpStream>, color: Position) -> Self {
+        let (tx, events) = mpsc::channel();
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                debug!("received '{line}'");
+                let event = match Message::parse(&line) {
+                    Some(msg) => NetEvent::Message(msg),
+                    None => NetEvent::Invalid(line),
+                };
+                if tx.send(event).is_err() {
+                    return;
+                }
+            }
+            let _ = tx.send(NetEvent::Disconnected);
+        });
+
+        Self {
+            stream,
+            events,
+            color,
+        }
+    }
+
+    /// Sends a message to the peer.
+    pub fn send(&mut self, msg: &Message) -> io::Result<()> {
+        debug!("sending '{msg}'");
+        writeln!(self.stream, "{msg}")?;
+        self.stream.flush()
+    }
+
+    /// Returns the next event from the peer without blocking.
+    pub fn try_recv(&self) -> Option<NetEvent> {
+        match self.events.try_recv() {
+            Ok(event) => Some(event),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(NetEvent::Disconnected),
+        }
+    }
+
+    /// The address of the other player.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let _ = self.send(&Message::Bye);
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+/// Waits for the other player to join. The host plays Red and moves first.
+pub struct Host {
+    listener: TcpListener,
+}
+
+impl Host {
+    /// Listens for players on `addr`.
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+        })
+    }
+
+    /// The address being listened on.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts connections until one completes the handshake.
+    pub fn accept(&self) -> io::Result<Connection> {
+        loop {
+            let (stream, addr) = self.listener.accept()?;
+            match handshake_host(stream) {
+                Ok(connection) => return Ok(connection),
+                Err(err) => debug!("rejected {addr}: {err}"),
+            }
+        }
+    }
+}
+
+fn handshake_host(mut stream: TcpStream) -> io::Result<Connection> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    match Message::parse(&line) {
+        Some(Message::Hello {
+            version: PROTOCOL_VERSION,
+        }) => {}
+        Some(Message::Hello { version }) => {
+            let reason = format!("unsupported protocol version {version}");
+            writeln!(stream, "{}", Message::Error(reason.clone()))?;
+            return Err(io::Error::new(io::ErrorKind::InvalidData, reason));
+        }
+        _ => return Err(unexpected(&line)),
+    }
+
+    let welcome = Message::Welcome {
+        version: PROTOCOL_VERSION,
+        color: Position::Black,
+    };
+    writeln!(stream, "{welcome}")?;
+    stream.set_read_timeout(None)?;
+
+    Ok(Connection::start(stream, reader, Position::Red))
+}
+
+/// Connects to a host and returns the connection once the handshake is done.
+pub fn join(addr: impl ToSocketAddrs) -> io::Result<Connection> {
+    let mut stream = TcpStream::connect(addr)?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    writeln!(
+        stream,
+        "{}",
+        Message::Hello {
+            version: PROTOCOL_VERSION
+        }
+    )?;
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    match Message::parse(&line) {
+        Some(Message::Welcome {
+            version: PROTOCOL_VERSION,
+            color,
+        }) => {
+            stream.set_read_timeout(None)?;
+            Ok(Connection::start(stream, reader, color))
+        }
+        Some(Message::Error(reason)) => Err(io::Error::new(io::ErrorKind::InvalidData, reason)),
+        _ => Err(unexpected(&line)),
+    }
+}
+
+fn unexpected(line: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected message '{}'", line.trim()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Blocks until the next event arrives.
+    fn recv(connection: &Connection) -> NetEvent {
+        loop {
+            if let Some(event) = connection.try_recv() {
+                return event;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn connect() -> (Connection, Connection) {
+        let host = Host::bind("127.0.0.1:0").unwrap();
+        let addr = host.local_addr().unwrap();
+        let joiner = thread::spawn(move || join(addr).unwrap());
+        let hosted = host.accept().unwrap();
+        (hosted, joiner.join().unwrap())
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        for msg in [
+            Message::Hello { version: 1 },
+            Message::Welcome {
+                version: 1,
+                color: Position::Black,
+            },
+            Message::Move { col: 0 },
+            Message::Move { col: 6 },
+            Message::Resign,
+            Message::Bye,
+            Message::Error("unsupported protocol version 2".into()),
+        ] {
+            assert_eq!(Message::parse(&msg.to_string()), Some(msg));
+        }
+
+        assert_eq!(Message::parse("move 8"), None);
+        assert_eq!(Message::parse("move 0"), None);
+        assert_eq!(Message::parse("welcome 1 green"), None);
+    }
+
+    #[test]
+    fn exchange_moves() {
+        let (mut host, mut joiner) = connect();
+        assert_eq!(host.color, Position::Red);
+        assert_eq!(joiner.color, Position::Black);
+
+        host.send(&Message::Move { col: 3 }).unwrap();
+        assert_eq!(recv(&joiner), NetEvent::Message(Message::Move { col: 3 }));
+
+        joiner.send(&Message::Resign).unwrap();
+        assert_eq!(recv(&host), NetEvent::Message(Message::Resign));
+    }
+
+    #[test]
+    fn disconnects() {
+        let (host, joiner) = connect();
+        drop(joiner);
+        assert_eq!(recv(&host), NetEvent::Message(Message::Bye));
+        assert_eq!(recv(&host), NetEvent::Disconnected);
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let host = Host::bind("127.0.0.1:0").unwrap();
+        let addr = host.local_addr().unwrap();
+        thread::spawn(move || host.accept());
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        writeln!(stream, "hello 99").unwrap();
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).unwrap();
+        assert_eq!(line.trim(), "error unsupported protocol version 99");
+    }
+}
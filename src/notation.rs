@@ -0,0 +1,147 @@
+//! Move sequences written as column numbers from 1 to 7, such as `4453` or `4 4 5 3`.
+//!
+//! This is the notation used by the engine protocol and the network protocol, and the one most
+//! Connect Four solvers and opening books use. Red always moves first.
+
+use std::fmt;
+
+use yamcts::GameState;
+
+use crate::connect_four::{CFGameState, CFMove, Position};
+
+/// Why a move sequence could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotationError {
+    /// Something other than a column from 1 to 7.
+    InvalidColumn(char),
+    /// The column of the move at `index` is already full.
+    ColumnFull {
+        /// Position of the move in the sequence, starting at 0.
+        index: usize,
+        /// The full column, starting at 0.
+        col: usize,
+    },
+    /// The move at `index` comes after the game has already ended.
+    GameOver {
+        /// Position of the move in the sequence, starting at 0.
+        index: usize,
+    },
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotationError::InvalidColumn(c) => write!(f, "invalid column '{c}'"),
+            NotationError::ColumnFull { index, col } => {
+                write!(f, "move {} plays in full column {}", index + 1, col + 1)
+            }
+            NotationError::GameOver { index } => {
+                write!(f, "move {} comes after the game has ended", index + 1)
+            }
+        }
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+/// Parses a single column from 1 to 7, returning it counted from 0.
+pub fn parse_col(c: char) -> Option<usize> {
+    c.to_digit(10)
+        .map(|d| d as usize)
+        .filter(|d| (1..=7).contains(d))
+        .map(|d| d - 1)
+}
+
+/// Parses a move sequence into columns counted from 0. Whitespace and commas are ignored.
+pub fn parse_cols(moves: &str) -> Result<Vec<usize>, NotationError> {
+    moves
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != ',')
+        .map(|c| parse_col(c).ok_or(NotationError::InvalidColumn(c)))
+        .collect()
+}
+
+/// Plays columns counted from 0 starting at `state`, checking that every move is legal.
+pub fn play_cols(
+    state: &CFGameState,
+    cols: impl IntoIterator<Item = usize>,
+) -> Result<CFGameState, NotationError> {
+    let mut state = state.clone();
+    for (index, col) in cols.into_iter().enumerate() {
+        if state.is_terminal_state().is_some() {
+            return Err(NotationError::GameOver { index });
+        }
+        if !state.can_play(col) {
+            return Err(NotationError::ColumnFull { index, col });
+        }
+
+        let color = state.next_player();
+        state = state.apply_move(CFMove { color, col });
+    }
+    Ok(state)
+}
+
+/// Parses a move sequence and plays it from the empty board.
+///
+/// ```
+/// use connect_four_mcts::{notation, Position};
+///
+/// let state = notation::parse("4453").unwrap();
+/// assert_eq!(state.pos(3, 5), Position::Red);
+/// assert_eq!(state.pos(3, 4), Position::Black);
+/// assert_eq!(state.next_player(), Position::Red);
+/// ```
+pub fn parse(moves: &str) -> Result<CFGameState, NotationError> {
+    play_cols(&CFGameState::default(), parse_cols(moves)?)
+}
+
+/// Parses a move sequence played from the empty board into moves, checking that they are legal.
+pub fn parse_moves(moves: &str) -> Result<Vec<CFMove>, NotationError> {
+    let cols = parse_cols(moves)?;
+    play_cols(&CFGameState::default(), cols.iter().copied())?;
+    Ok(cols
+        .into_iter()
+        .zip([Position::Red, Position::Black].into_iter().cycle())
+        .map(|(col, color)| CFMove { color, col })
+        .collect())
+}
+
+/// Writes moves in the compact form accepted by [`parse`].
+pub fn format(moves: &[CFMove]) -> String {
+    moves
+        .iter()
+        .map(|m| char::from_digit(m.col as u32 + 1, 10).unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let cols = parse_cols("4 4,5 3").unwrap();
+        assert_eq!(cols, [3, 3, 4, 2]);
+
+        let moves = cols
+            .iter()
+            .zip([Position::Red, Position::Black].iter().cycle())
+            .map(|(&col, &color)| CFMove { color, col })
+            .collect::<Vec<_>>();
+        assert_eq!(format(&moves), "4453");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse("48").unwrap_err(), NotationError::InvalidColumn('8'));
+        assert_eq!(parse("4x").unwrap_err(), NotationError::InvalidColumn('x'));
+        assert_eq!(
+            parse("1111111").unwrap_err(),
+            NotationError::ColumnFull { index: 6, col: 0 }
+        );
+        assert_eq!(
+            parse("12121212").unwrap_err(),
+            NotationError::GameOver { index: 7 }
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! Records of whole games, kept as the moves played from the empty board.
+
+use yamcts::GameState;
+
+use crate::{
+    connect_four::{CFGameState, CFMove, Position},
+    notation::{self, NotationError},
+};
+
+/// How a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum GameResult {
+    /// Red won.
+    Red,
+    /// Black won.
+    Black,
+    /// The board filled up without four in a row.
+    Draw,
+}
+
+impl GameResult {
+    /// Converts the result of a finished [`CFGameState`].
+    pub fn from_terminal(result: Position) -> Self {
+        match result {
+            Position::Red => GameResult::Red,
+            Position::Black => GameResult::Black,
+            Position::Empty => GameResult::Draw,
+        }
+    }
+}
+
+/// A game from the empty board, with Red moving first.
+///
+/// With the `serde` feature it is written as
+/// `{"red": "Alice", "black": "AI", "moves": "4453", "result": null}`, where the names are
+/// optional and `moves` uses the [`notation`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameRecord {
+    /// Who played Red.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub red: Option<String>,
+    /// Who played Black.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub black: Option<String>,
+    /// The moves in the order they were played.
+    #[cfg_attr(feature = "serde", serde(with = "moves_notation"))]
+    pub moves: Vec<CFMove>,
+    /// How the game ended, `None` while it is still going. This also covers resignations, so it
+    /// is not always the result of the final position.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub result: Option<GameResult>,
+}
+
+impl GameRecord {
+    /// Reads the moves of a game, taking its result from the final position.
+    pub fn from_notation(moves: &str) -> Result<Self, NotationError> {
+        let mut record = GameRecord {
+            moves: notation::parse_moves(moves)?,
+            ..Default::default()
+        };
+        record.result = record
+            .state()?
+            .is_terminal_state()
+            .map(GameResult::from_terminal);
+        Ok(record)
+    }
+
+    /// Replays the moves to get the current position.
+    pub fn state(&self) -> Result<CFGameState, NotationError> {
+        notation::play_cols(&CFGameState::default(), self.moves.iter().map(|m| m.col))
+    }
+}
+
+#[cfg(feature = "serde")]
+mod moves_notation {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use crate::{connect_four::CFMove, notation};
+
+    pub fn serialize<S: Serializer>(moves: &[CFMove], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&notation::format(moves))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<CFMove>, D::Error> {
+        let moves = String::deserialize(deserializer)?;
+        notation::parse_moves(&moves).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results() {
+        let record = GameRecord::from_notation("4453").unwrap();
+        assert_eq!(record.moves.len(), 4);
+        assert_eq!(record.moves[1].color, Position::Black);
+        assert_eq!(record.result, None);
+
+        let record = GameRecord::from_notation("1212121").unwrap();
+        assert_eq!(record.result, Some(GameResult::Red));
+        assert_eq!(notation::format(&record.moves), "1212121");
+
+        assert_eq!(
+            GameRecord::from_notation("11111111").unwrap_err(),
+            NotationError::ColumnFull { index: 6, col: 0 }
+        );
+    }
+}
@@ -0,0 +1,123 @@
+//! A playout policy for Connect Four that knows about immediate threats.
+//!
+//! Purely random playouts often miss a win that is right there, or let the opponent complete
+//! four in a row on the next move, which makes the search misjudge sharp positions. The
+//! [`HeuristicRollout`] plays the obvious moves instead, and only picks at random among the rest.
+
+use rand::{rngs::SmallRng, seq::SliceRandom, Rng};
+
+use crate::{
+    connect_four::{CFGameState, CFMove},
+    search::Rollout,
+};
+
+/// In order of preference: win at once, block the opponent's immediate win, and otherwise play
+/// a random move that does not let the opponent win on top of it.
+///
+/// ```
+/// use connect_four_mcts::{notation, HeuristicRollout, Search};
+///
+/// let search = Search::new(notation::parse("4453").unwrap()).rollout(HeuristicRollout::new(0.1));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct HeuristicRollout {
+    epsilon: f64,
+}
+
+impl HeuristicRollout {
+    /// Plays a uniformly random move instead with probability `epsilon`, so that the playouts
+    /// still explore lines the heuristic would never pick. It is kept between 0 and 1, and NaN
+    /// counts as 0.
+    pub fn new(epsilon: f64) -> Self {
+        let epsilon = if epsilon.is_nan() { 0.0 } else { epsilon };
+        Self {
+            epsilon: epsilon.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl Default for HeuristicRollout {
+    fn default() -> Self {
+        Self::new(0.05)
+    }
+}
+
+impl Rollout<CFGameState> for HeuristicRollout {
+    fn choose(&self, state: &CFGameState, rng: &mut SmallRng) -> CFMove {
+        let color = state.next_player();
+        let cols = (0..7)
+            .filter(|&col| state.can_play(col))
+            .collect::<Vec<_>>();
+        let play = |col| CFMove { color, col };
+
+        if rng.gen_bool(self.epsilon) {
+            return play(*cols.choose(rng).unwrap());
+        }
+
+        let win = state.winning_cols(color).first().copied();
+        if let Some(col) = win.or_else(|| state.winning_cols(color.opponent()).first().copied()) {
+            return play(col);
+        }
+
+        let poisoned = state.poisoned_cols(color);
+        let safe = cols
+            .iter()
+            .copied()
+            .filter(|col| !poisoned.contains(col))
+            .collect::<Vec<_>>();
+        let candidates = if safe.is_empty() { &cols } else { &safe };
+
+        play(*candidates.choose(rng).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::notation;
+
+    fn choose(moves: &str) -> usize {
+        let state = notation::parse(moves).unwrap();
+        let mut rng = SmallRng::seed_from_u64(35);
+        HeuristicRollout::new(0.0).choose(&state, &mut rng).col
+    }
+
+    #[test]
+    fn takes_wins_and_blocks() {
+        // Red completes the first column rather than blocking Black
+        assert_eq!(choose("121212"), 0);
+        // Black has to stop Red in the first column
+        assert_eq!(choose("12121"), 0);
+        // Red wins along the bottom row
+        assert_eq!(choose("15263"), 3);
+    }
+
+    #[test]
+    fn avoids_playing_under_threats() {
+        // Black has three in the second row from the bottom, so Red must not play the bottom
+        // of column 4
+        let state = notation::parse("11337272").unwrap();
+        let mut rng = SmallRng::seed_from_u64(35);
+        for _ in 0..200 {
+            let col = HeuristicRollout::new(0.0).choose(&state, &mut rng).col;
+            assert_ne!(col, 3);
+        }
+    }
+
+    #[test]
+    fn keeps_epsilon_in_range() {
+        assert_eq!(HeuristicRollout::new(-1.0).epsilon, 0.0);
+        assert_eq!(HeuristicRollout::new(2.0).epsilon, 1.0);
+        assert_eq!(HeuristicRollout::new(f64::NAN).epsilon, 0.0);
+
+        // a NaN epsilon plays the heuristic's moves
+        let state = notation::parse("11337272").unwrap();
+        let mut rng = SmallRng::seed_from_u64(35);
+        let rollout = HeuristicRollout::new(f64::NAN);
+        for _ in 0..200 {
+            assert_ne!(rollout.choose(&state, &mut rng).col, 3);
+        }
+    }
+}
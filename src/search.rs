@@ -0,0 +1,1386 @@
+//! Monte Carlo Tree Search over any [`yamcts::GameState`].
+
+use std::{
+    cmp::Reverse,
+    collections::VecDeque,
+    fmt,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use rand::{rngs::SmallRng, Rng as _, SeedableRng};
+use yamcts::GameState;
+
+/// Picks the moves of playouts that estimate how good a position is.
+pub trait Rollout<T: GameState>: Send {
+    /// Chooses a move in `state`, which is not terminal.
+    fn choose(&self, state: &T, rng: &mut SmallRng) -> T::Move;
+}
+
+/// Plays uniformly random moves, the default. This is what [`GameState::random_move`] does, but
+/// with a `rand` generator.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomRollout;
+
+impl<T: GameState> Rollout<T> for RandomRollout {
+    fn choose(&self, state: &T, rng: &mut SmallRng) -> T::Move {
+        let moves = state.all_moves();
+        moves[rng.gen_range(0..moves.len())]
+    }
+}
+
+struct Node<T: GameState> {
+    state: T,
+    action: Option<T::Move>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    n: u64,
+    w: f64,
+    /// All-moves-as-first statistics: playouts through the parent in which the player to move
+    /// there made this move at any later point.
+    amaf_n: u64,
+    amaf_w: f64,
+    proven: Option<Outcome>,
+}
+
+impl<T: GameState> Node<T> {
+    fn new(state: T, action: Option<T::Move>, parent: Option<usize>) -> Self {
+        Self {
+            state,
+            action,
+            parent,
+            children: Vec::new(),
+            n: 0,
+            w: 0.0,
+            amaf_n: 0,
+            amaf_w: 0.0,
+            proven: None,
+        }
+    }
+
+    fn win_rate(&self) -> f64 {
+        if self.n == 0 {
+            0.0
+        } else {
+            self.w / self.n as f64
+        }
+    }
+}
+
+/// How a game ends for the player who made a move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The player wins.
+    Win,
+    /// The opponent wins.
+    Loss,
+    /// Nobody wins.
+    Draw,
+}
+
+impl Outcome {
+    /// The same ending for the other player.
+    pub fn opponent(self) -> Self {
+        match self {
+            Outcome::Win => Outcome::Loss,
+            Outcome::Loss => Outcome::Win,
+            Outcome::Draw => Outcome::Draw,
+        }
+    }
+}
+
+/// Statistics gathered for one of the moves available at the root.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MoveStats<M> {
+    /// The move from the root.
+    pub action: M,
+    /// How many iterations went through this move.
+    pub visits: u64,
+    /// Fraction of playouts through this move won by the side to move at the root.
+    pub win_rate: f64,
+    /// How the game ends after this move with best play, once the solver has proven it.
+    pub proven: Option<Outcome>,
+}
+
+/// A single Monte Carlo search tree which is advanced one iteration at a time, so that the
+/// caller decides when to stop and can inspect the statistics while searching.
+pub struct Search<T: GameState> {
+    nodes: Vec<Node<T>>,
+    exploration_factor: f64,
+    draw_value: f64,
+    solver: bool,
+    rave: Option<f64>,
+    max_nodes: usize,
+    iterations: u64,
+    rollout: Box<dyn Rollout<T>>,
+    leaf_value: Option<LeafValue<T>>,
+    rng: SmallRng,
+    /// The moves of the current iteration below the root, kept to save allocations.
+    moves: Vec<T::Move>,
+}
+
+type LeafValue<T> = Box<dyn Fn(&T) -> f64 + Send>;
+
+impl<T: GameState> Search<T> {
+    /// Starts a search from `state` with the default exploration constant of sqrt(2).
+    pub fn new(state: T) -> Self {
+        Self {
+            nodes: vec![Node::new(state, None, None)],
+            exploration_factor: 2.0_f64.sqrt(),
+            draw_value: 0.0,
+            solver: true,
+            rave: None,
+            max_nodes: usize::MAX,
+            iterations: 0,
+            rollout: Box::new(RandomRollout),
+            leaf_value: None,
+            rng: SmallRng::from_entropy(),
+            moves: Vec::new(),
+        }
+    }
+
+    /// Sets the UCT exploration constant, higher values try more moves.
+    pub fn exploration_factor(mut self, exploration_factor: f64) -> Self {
+        self.exploration_factor = exploration_factor;
+        self
+    }
+
+    /// Sets what a drawn playout is worth to both players, from 0 like a loss (the default) to 1
+    /// like a win. 0.5 makes the AI prefer a draw to a position it is more likely to lose.
+    pub fn draw_value(mut self, draw_value: f64) -> Self {
+        self.draw_value = draw_value;
+        self
+    }
+
+    /// Turns the MCTS-Solver on or off, it is on by default. The solver marks finished games as
+    /// won, lost or drawn and proves positions from them: a move is lost if the opponent has a
+    /// winning reply, and won if all replies lose. The search then stops sampling proven
+    /// positions, never picks a proven loss and always picks a proven win.
+    pub fn solver(mut self, solver: bool) -> Self {
+        self.solver = solver;
+        self
+    }
+
+    /// Turns on RAVE (rapid action value estimation), which blends the win rate of a move with
+    /// its all-moves-as-first (AMAF) win rate: that of every playout in which the same player made
+    /// the move later on. This suits games like Connect Four, where the same moves are often good
+    /// in a different order. AMAF values are available early but biased, so their weight
+    /// `sqrt(k / (3 n + k))` falls as a move gets more visits `n`, where `k` is the number of
+    /// visits at which both count about as much. It must be finite and above 0.
+    pub fn rave(mut self, k: f64) -> Self {
+        assert!(
+            k.is_finite() && k > 0.0,
+            "the RAVE parameter must be above 0"
+        );
+        self.rave = Some(k);
+        self
+    }
+
+    /// Stops growing the tree once it holds `max_nodes` positions, which bounds the memory of a
+    /// long search. Iterations go on from the leaves of the tree, so its statistics keep
+    /// improving. Trees are unbounded by default.
+    pub fn max_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = max_nodes;
+        self
+    }
+
+    /// Seeds the random number generator, so that the same number of iterations from the same
+    /// position always builds the same tree. Searches are seeded from entropy otherwise.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = SmallRng::seed_from_u64(seed);
+        self
+    }
+
+    /// Sets the policy used to play out positions, [`RandomRollout`] by default.
+    pub fn rollout(mut self, rollout: impl Rollout<T> + 'static) -> Self {
+        self.rollout = Box::new(rollout);
+        self
+    }
+
+    /// Scores new leaves with `value` instead of playing them out. It returns the chance from 0
+    /// to 1 that the player who moved into the position goes on to win.
+    pub fn evaluate_leaves(mut self, value: impl Fn(&T) -> f64 + Send + 'static) -> Self {
+        self.leaf_value = Some(Box::new(value));
+        self
+    }
+
+    /// How many iterations have run so far.
+    pub fn iterations(&self) -> u64 {
+        self.iterations
+    }
+
+    /// Runs one select / expand / playout / backpropagate cycle.
+    pub fn iterate(&mut self) {
+        let leaf = self.select();
+
+        // the root is expanded right away, so that a single iteration already rates a move
+        let node = &self.nodes[leaf];
+        let grow = node.n > 0 && self.nodes.len() < self.max_nodes;
+        let idx = if (grow || leaf == 0)
+            && node.proven.is_none()
+            && node.state.is_terminal_state().is_none()
+        {
+            let children = self.expand(leaf);
+            children[self.rng.gen_range(0..children.len())]
+        } else {
+            leaf
+        };
+
+        let mut moves = std::mem::take(&mut self.moves);
+        moves.clear();
+
+        // the value of the iteration for the player who moved into `idx`, and for the other one
+        let state = &self.nodes[idx].state;
+        let (value, other) = match (&self.leaf_value, state.is_terminal_state()) {
+            // a proven position needs no playout
+            _ if self.nodes[idx].proven.is_some() => self.values(self.nodes[idx].proven.unwrap()),
+            (Some(value), None) => {
+                let value = value(state);
+                (value, 1.0 - value)
+            }
+            _ => {
+                let result = self.playout(idx, &mut moves);
+                self.values(self.outcome(idx, &result))
+            }
+        };
+
+        self.backpropagate(idx, value, other);
+        if self.rave.is_some() {
+            self.update_amaf(idx, &mut moves, value, other);
+        }
+        self.moves = moves;
+        self.iterations += 1;
+    }
+
+    /// Runs a fixed number of iterations.
+    pub fn run_iterations(&mut self, iterations: u32) {
+        for _ in 0..iterations {
+            self.iterate();
+        }
+    }
+
+    /// Keeps iterating until `duration` has elapsed.
+    pub fn run_for(&mut self, duration: Duration) {
+        let end = Instant::now() + duration;
+        while Instant::now() < end {
+            for _ in 0..256 {
+                self.iterate();
+            }
+        }
+    }
+
+    /// Makes the position after `action` the new root, keeping the part of the tree below it.
+    /// Returns how many visits were kept, which is 0 if the move had not been searched yet.
+    pub fn advance(&mut self, action: T::Move) -> u64 {
+        self.iterations = 0;
+
+        let child = self.nodes[0]
+            .children
+            .iter()
+            .copied()
+            .find(|&c| self.nodes[c].action == Some(action));
+        let Some(child) = child else {
+            let state = self.nodes[0].state.apply_move(action);
+            self.nodes = vec![Node::new(state, None, None)];
+            return 0;
+        };
+
+        // copy the subtree breadth first, so that every parent comes before its children
+        let mut old = std::mem::take(&mut self.nodes)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        let mut queue = VecDeque::from([(child, None)]);
+        while let Some((idx, parent)) = queue.pop_front() {
+            let mut node = old[idx].take().unwrap();
+            let new_idx = self.nodes.len();
+            if let Some(parent) = parent {
+                let parent: &mut Node<T> = &mut self.nodes[parent];
+                parent.children.push(new_idx);
+            } else {
+                node.action = None;
+            }
+            node.parent = parent;
+            queue.extend(node.children.drain(..).map(|c| (c, Some(new_idx))));
+            self.nodes.push(node);
+        }
+
+        self.nodes[0].n
+    }
+
+    /// Statistics for every move from the root that has been expanded so far.
+    pub fn root_stats(&self) -> Vec<MoveStats<T::Move>> {
+        self.nodes[0]
+            .children
+            .iter()
+            .map(|&c| {
+                let child = &self.nodes[c];
+                MoveStats {
+                    action: child.action.unwrap(),
+                    visits: child.n,
+                    win_rate: child.win_rate(),
+                    proven: child.proven,
+                }
+            })
+            .collect()
+    }
+
+    /// The most visited move from the root, if any has been searched, or a proven win.
+    pub fn best(&self) -> Option<MoveStats<T::Move>> {
+        self.snapshot().best()
+    }
+
+    /// The expected line of play from the root: the forced win once the solver has found one,
+    /// and otherwise the most visited move at each step, avoiding proven losses while there is
+    /// another move. It ends where the search has not gone any deeper.
+    pub fn principal_variation(&self) -> Vec<T::Move> {
+        let mut pv = Vec::new();
+        let mut idx = 0;
+        loop {
+            let children = &self.nodes[idx].children;
+            let proven = |c: usize, outcome| self.nodes[c].proven == Some(outcome);
+            let all_lose = children.iter().all(|&c| proven(c, Outcome::Loss));
+            let next = children
+                .iter()
+                .copied()
+                .find(|&c| proven(c, Outcome::Win))
+                .or_else(|| {
+                    children
+                        .iter()
+                        .copied()
+                        .filter(|&c| all_lose || !proven(c, Outcome::Loss))
+                        .max_by_key(|&c| self.nodes[c].n)
+                });
+
+            match next {
+                Some(c) if self.nodes[c].n > 0 || self.nodes[c].proven.is_some() => {
+                    pv.push(self.nodes[c].action.unwrap());
+                    idx = c;
+                }
+                _ => return pv,
+            }
+        }
+    }
+
+    /// Copies the top `max_depth` levels of the tree below the root, for inspecting the search.
+    pub fn tree(&self, max_depth: usize) -> TreeNode<T> {
+        self.subtree(0, max_depth)
+    }
+
+    fn subtree(&self, idx: usize, max_depth: usize) -> TreeNode<T> {
+        let node = &self.nodes[idx];
+        let children = if max_depth == 0 {
+            Vec::new()
+        } else {
+            node.children
+                .iter()
+                .map(|&c| self.subtree(c, max_depth - 1))
+                .collect()
+        };
+        TreeNode {
+            action: node.action,
+            state: node.state.clone(),
+            visits: node.n,
+            win_rate: node.win_rate(),
+            proven: node.proven,
+            children,
+        }
+    }
+
+    /// Copies the current root statistics.
+    pub fn snapshot(&self) -> Snapshot<T::Move> {
+        Snapshot {
+            iterations: self.iterations,
+            stats: self.root_stats(),
+            pv: self.principal_variation(),
+        }
+    }
+
+    /// upper confidence bound calculation, unvisited nodes are always tried first
+    fn uct(&self, idx: usize, parent_ln_n: f64) -> f64 {
+        let node = &self.nodes[idx];
+        if node.n == 0 {
+            return f64::INFINITY;
+        }
+
+        let value = match self.rave {
+            Some(k) if node.amaf_n > 0 => {
+                let beta = (k / (3.0 * node.n as f64 + k)).sqrt();
+                (1.0 - beta) * node.win_rate() + beta * node.amaf_w / node.amaf_n as f64
+            }
+            _ => node.win_rate(),
+        };
+        value + self.exploration_factor * (parent_ln_n / node.n as f64).sqrt()
+    }
+
+    fn select(&self) -> usize {
+        let mut idx = 0;
+        loop {
+            let node = &self.nodes[idx];
+            // a proven root is still searched, so that the statistics of its moves keep growing
+            if node.children.is_empty() || (idx != 0 && node.proven.is_some()) {
+                return idx;
+            }
+
+            // proven losses are skipped, unless every move loses and the position is a proven win
+            let all_lose = node.proven == Some(Outcome::Win);
+            let parent_ln_n = (node.n as f64).ln();
+            idx = *node
+                .children
+                .iter()
+                .filter(|&&c| all_lose || self.nodes[c].proven != Some(Outcome::Loss))
+                .max_by(|&&a, &&b| {
+                    self.uct(a, parent_ln_n)
+                        .total_cmp(&self.uct(b, parent_ln_n))
+                })
+                .unwrap();
+        }
+    }
+
+    fn expand(&mut self, idx: usize) -> Vec<usize> {
+        let state = self.nodes[idx].state.clone();
+        for action in state.all_moves() {
+            let child = Node::new(state.apply_move(action), Some(action), Some(idx));
+            self.nodes.push(child);
+            let child_idx = self.nodes.len() - 1;
+            self.nodes[idx].children.push(child_idx);
+
+            if self.solver {
+                if let Some(result) = self.nodes[child_idx].state.is_terminal_state() {
+                    self.nodes[child_idx].proven = Some(self.outcome(child_idx, &result));
+                }
+            }
+        }
+        if self.solver {
+            self.prove(idx);
+        }
+        self.nodes[idx].children.clone()
+    }
+
+    /// Proves `idx` from its children if they allow it, and then its ancestors in turn.
+    fn prove(&mut self, mut idx: usize) {
+        loop {
+            let node = &self.nodes[idx];
+            if node.proven.is_some() || node.children.is_empty() {
+                return;
+            }
+
+            // the children are the other player's moves
+            let children = || node.children.iter().map(|&c| self.nodes[c].proven);
+            let proven = if children().any(|p| p == Some(Outcome::Win)) {
+                Outcome::Loss
+            } else if children().all(|p| p == Some(Outcome::Loss)) {
+                Outcome::Win
+            } else if children().all(|p| p.is_some()) {
+                Outcome::Draw
+            } else {
+                return;
+            };
+
+            self.nodes[idx].proven = Some(proven);
+            match self.nodes[idx].parent {
+                Some(parent) => idx = parent,
+                None => return,
+            }
+        }
+    }
+
+    /// How the finished game `result` ends for the player who moved into `idx`. The players
+    /// alternate along the tree, so a result that is a win for neither `idx` nor its parent is a
+    /// draw.
+    fn outcome(&self, idx: usize, result: &T::UserData) -> Outcome {
+        let wins = |i: usize| self.nodes[i].state.terminal_is_win(result);
+        if wins(idx) {
+            Outcome::Win
+        } else if self.nodes[idx].parent.is_some_and(wins) {
+            Outcome::Loss
+        } else {
+            Outcome::Draw
+        }
+    }
+
+    /// Plays out the position at `idx`, adding the moves to `moves` when they are needed for RAVE.
+    fn playout(&mut self, idx: usize, moves: &mut Vec<T::Move>) -> T::UserData {
+        let mut state = self.nodes[idx].state.clone();
+        loop {
+            if let Some(result) = state.is_terminal_state() {
+                return result;
+            }
+            let action = self.rollout.choose(&state, &mut self.rng);
+            if self.rave.is_some() {
+                moves.push(action);
+            }
+            state = state.apply_move(action);
+        }
+    }
+
+    /// What `outcome` is worth to the player it happened to, and to the other player.
+    fn values(&self, outcome: Outcome) -> (f64, f64) {
+        match outcome {
+            Outcome::Win => (1.0, 0.0),
+            Outcome::Loss => (0.0, 1.0),
+            Outcome::Draw => (self.draw_value, self.draw_value),
+        }
+    }
+
+    /// Each level up the tree is the other player's move, so `value` and `other` take turns.
+    fn backpropagate(&mut self, idx: usize, mut value: f64, mut other: f64) {
+        let mut idx = Some(idx);
+        while let Some(i) = idx {
+            let node = &mut self.nodes[i];
+            node.n += 1;
+            node.w += value;
+            std::mem::swap(&mut value, &mut other);
+            idx = node.parent;
+        }
+    }
+
+    /// Updates the AMAF statistics of the children of every node from `idx` up to the root.
+    /// `moves` holds the moves of the playout from `idx`, `value` and `other` are as in
+    /// [`Search::backpropagate`].
+    fn update_amaf(&mut self, idx: usize, moves: &mut Vec<T::Move>, value: f64, other: f64) {
+        // the moves from the root to the end of the playout
+        let playout_len = moves.len();
+        let mut path = idx;
+        while let Some(parent) = self.nodes[path].parent {
+            moves.push(self.nodes[path].action.unwrap());
+            path = parent;
+        }
+        let mut depth = moves.len() - playout_len;
+        moves[playout_len..].reverse();
+        moves.rotate_left(playout_len);
+
+        // going back from the end, `seen[i % 2]` holds the moves at `i`, `i + 2` and so on, which
+        // are those of the player to move at depth `i`
+        let mut seen: [Vec<T::Move>; 2] = [Vec::new(), Vec::new()];
+        let mut node = idx;
+        // the players of the children of `node`
+        let (mut value, mut other) = (other, value);
+        for i in (0..=moves.len()).rev() {
+            if let Some(&action) = moves.get(i) {
+                if !seen[i % 2].contains(&action) {
+                    seen[i % 2].push(action);
+                }
+            }
+            if i != depth {
+                continue;
+            }
+
+            for c in 0..self.nodes[node].children.len() {
+                let child = self.nodes[node].children[c];
+                let child = &mut self.nodes[child];
+                if seen[i % 2].contains(&child.action.unwrap()) {
+                    child.amaf_n += 1;
+                    child.amaf_w += value;
+                }
+            }
+            std::mem::swap(&mut value, &mut other);
+            match self.nodes[node].parent {
+                Some(parent) => node = parent,
+                None => break,
+            }
+            depth -= 1;
+        }
+    }
+}
+
+/// A copy of part of a search tree, see [`Search::tree`].
+#[derive(Debug, Clone)]
+pub struct TreeNode<T: GameState> {
+    /// The move into this position, `None` at the root.
+    pub action: Option<T::Move>,
+    /// The position.
+    pub state: T,
+    /// How many iterations went through this position.
+    pub visits: u64,
+    /// Fraction of them won by the player who made `action`.
+    pub win_rate: f64,
+    /// How the game ends for the player who made `action` with best play, if the solver knows.
+    pub proven: Option<Outcome>,
+    /// The positions after each move searched from here.
+    pub children: Vec<TreeNode<T>>,
+}
+
+impl<T: GameState> TreeNode<T> {
+    /// Adds the statistics of `other`, a tree of the same position from another search, like
+    /// [`Snapshot::merge`] does for the root.
+    pub fn merge(&mut self, other: TreeNode<T>) {
+        let visits = self.visits + other.visits;
+        if visits > 0 {
+            self.win_rate = (self.win_rate * self.visits as f64
+                + other.win_rate * other.visits as f64)
+                / visits as f64;
+        }
+        self.visits = visits;
+        self.proven = self.proven.or(other.proven);
+
+        for child in other.children {
+            match self.children.iter_mut().find(|c| c.action == child.action) {
+                Some(mine) => mine.merge(child),
+                None => self.children.push(child),
+            }
+        }
+    }
+
+    /// Drops the positions with fewer than `min_visits` visits, and sorts the remaining moves
+    /// from the most to the least visited.
+    pub fn prune(&mut self, min_visits: u64) {
+        self.children.retain(|c| c.visits >= min_visits);
+        self.children.sort_by_key(|c| Reverse(c.visits));
+        for child in &mut self.children {
+            child.prune(min_visits);
+        }
+    }
+
+    /// The number of positions in the tree.
+    pub fn size(&self) -> usize {
+        1 + self.children.iter().map(TreeNode::size).sum::<usize>()
+    }
+}
+
+/// The root statistics of a search at some point in time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot<M> {
+    /// How many iterations had run.
+    pub iterations: u64,
+    /// Statistics for each move from the root.
+    pub stats: Vec<MoveStats<M>>,
+    /// The expected line of play from the root, see [`Search::principal_variation`].
+    pub pv: Vec<M>,
+}
+
+impl<M: Copy> Snapshot<M> {
+    /// The most visited move.
+    pub fn best(&self) -> Option<MoveStats<M>> {
+        self.select(MoveSelection::MostVisits)
+    }
+
+    /// The move to play according to `selection`. A proven win is played whatever the rule, and
+    /// a proven loss only if every move loses.
+    pub fn select(&self, selection: MoveSelection) -> Option<MoveStats<M>> {
+        if let Some(win) = self.forced_win() {
+            return Some(win);
+        }
+        let mut stats = self
+            .stats
+            .iter()
+            .copied()
+            .filter(|s| s.proven != Some(Outcome::Loss))
+            .collect::<Vec<_>>();
+        if stats.is_empty() {
+            stats = self.stats.clone();
+        }
+
+        let by_value = |a: &&MoveStats<M>, b: &&MoveStats<M>| {
+            a.win_rate
+                .total_cmp(&b.win_rate)
+                .then(a.visits.cmp(&b.visits))
+        };
+        let most_visits = stats.iter().max_by_key(|s| s.visits);
+        let highest_value = stats.iter().filter(|s| s.visits > 0).max_by(by_value);
+
+        match selection {
+            MoveSelection::MostVisits => most_visits.copied(),
+            MoveSelection::HighestValue => highest_value.copied(),
+            MoveSelection::RobustMax => {
+                let most_visits = most_visits?;
+                if highest_value.is_some_and(|h| h.visits == most_visits.visits) {
+                    return highest_value.copied();
+                }
+                stats.iter().copied().max_by(|a, b| {
+                    (a.win_rate * a.visits as f64).total_cmp(&(b.win_rate * b.visits as f64))
+                })
+            }
+        }
+    }
+}
+
+impl<M: Copy> Snapshot<M> {
+    /// A move the solver has proven to win.
+    pub fn forced_win(&self) -> Option<MoveStats<M>> {
+        self.stats
+            .iter()
+            .copied()
+            .find(|s| s.proven == Some(Outcome::Win))
+    }
+}
+
+impl<M: Copy + PartialEq> Snapshot<M> {
+    /// Combines the root statistics of independent searches of the same position, adding up
+    /// the visits of each move and weighting the win rates by them. The principal variation is
+    /// that of the search that spent the most visits on the best merged move.
+    pub fn merge<'a>(snapshots: impl IntoIterator<Item = &'a Snapshot<M>>) -> Self
+    where
+        M: 'a,
+    {
+        let snapshots = snapshots.into_iter().collect::<Vec<_>>();
+        let mut merged = Snapshot {
+            iterations: 0,
+            stats: Vec::new(),
+            pv: Vec::new(),
+        };
+        for snapshot in &snapshots {
+            merged.iterations += snapshot.iterations;
+            for stats in &snapshot.stats {
+                match merged.stats.iter_mut().find(|s| s.action == stats.action) {
+                    Some(total) => {
+                        let visits = total.visits + stats.visits;
+                        if visits > 0 {
+                            total.win_rate = (total.win_rate * total.visits as f64
+                                + stats.win_rate * stats.visits as f64)
+                                / visits as f64;
+                        }
+                        total.visits = visits;
+                        total.proven = total.proven.or(stats.proven);
+                    }
+                    None => merged.stats.push(*stats),
+                }
+            }
+        }
+
+        if let Some(best) = merged.best() {
+            let visits = |snapshot: &Snapshot<M>| {
+                snapshot
+                    .stats
+                    .iter()
+                    .find(|s| s.action == best.action)
+                    .map_or(0, |s| s.visits)
+            };
+            if let Some(snapshot) = snapshots
+                .iter()
+                .filter(|s| s.pv.first() == Some(&best.action))
+                .max_by_key(|s| visits(s))
+            {
+                merged.pv = snapshot.pv.clone();
+            }
+        }
+        merged
+    }
+}
+
+/// The number of threads a parallel search uses unless told otherwise, one per CPU core.
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// The settings of a [`Search`] that programs let their users choose, to build the search of
+/// every thread of a parallel search the same way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchConfig {
+    /// See [`Search::exploration_factor`].
+    pub exploration_factor: f64,
+    /// See [`Search::draw_value`].
+    pub draw_value: f64,
+    /// See [`Search::rave`], off when `None`.
+    pub rave: Option<f64>,
+    /// Seeds the search of thread `i` with `seed + i`, see [`Search::seed`].
+    pub seed: Option<u64>,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            exploration_factor: std::f64::consts::SQRT_2,
+            draw_value: 0.0,
+            rave: None,
+            seed: None,
+        }
+    }
+}
+
+impl SearchConfig {
+    /// A search of `state` for the thread numbered `thread`.
+    pub fn search<T: GameState>(&self, state: T, thread: usize) -> Search<T> {
+        let search = Search::new(state)
+            .exploration_factor(self.exploration_factor)
+            .draw_value(self.draw_value);
+        let search = match self.rave {
+            Some(k) => search.rave(k),
+            None => search,
+        };
+        match self.seed {
+            Some(seed) => search.seed(seed.wrapping_add(thread as u64)),
+            None => search,
+        }
+    }
+}
+
+/// How the move to play is picked from the root statistics at the end of a search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MoveSelection {
+    /// The move with the most visits, which the search has come to trust the most.
+    #[default]
+    MostVisits,
+    /// The move with the highest win rate, even if it has only been tried a few times.
+    HighestValue,
+    /// The move with both the most visits and the highest win rate. If no move has both, the one
+    /// with the most wins, counting a visit by its win rate.
+    RobustMax,
+}
+
+impl fmt::Display for MoveSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MoveSelection::MostVisits => "most-visits",
+            MoveSelection::HighestValue => "highest-value",
+            MoveSelection::RobustMax => "robust-max",
+        })
+    }
+}
+
+impl FromStr for MoveSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "most-visits" => Ok(MoveSelection::MostVisits),
+            "highest-value" => Ok(MoveSelection::HighestValue),
+            "robust-max" => Ok(MoveSelection::RobustMax),
+            _ => Err(format!(
+                "unknown move selection '{s}', expected most-visits, highest-value or robust-max"
+            )),
+        }
+    }
+}
+
+/// How long a [`SearchHandle`] keeps searching.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Budget {
+    /// Until the time has elapsed.
+    Time(Duration),
+    /// A number of iterations shared out between the threads. Unlike a time limit this does not
+    /// depend on the speed of the machine, so seeded searches always come to the same result.
+    Iterations(u32),
+    /// Until stopped.
+    Infinite,
+}
+
+/// Searches running on their own threads, which publish a snapshot of their progress every few
+/// hundred iterations.
+pub struct SearchHandle<T: GameState> {
+    stop: Arc<AtomicBool>,
+    snapshots: Arc<Mutex<Vec<Snapshot<T::Move>>>>,
+    threads: Vec<JoinHandle<Search<T>>>,
+}
+
+impl<T: GameState + Send + 'static> SearchHandle<T>
+where
+    T::Move: Send,
+{
+    /// Runs `search` until its `budget` is used up or it is stopped.
+    pub fn spawn(search: Search<T>, budget: Budget) -> Self {
+        Self::spawn_parallel(vec![search], budget)
+    }
+
+    /// Runs each search on its own thread, which is root parallelization when they all start
+    /// from the same position: the trees never share anything until their root statistics are
+    /// merged in [`SearchHandle::snapshot`].
+    ///
+    /// ```
+    /// use connect_four_mcts::search::{self, Budget, Search, SearchHandle};
+    /// use connect_four_mcts::CFGameState;
+    /// use std::time::Duration;
+    ///
+    /// let searches = (0..search::default_threads())
+    ///     .map(|_| Search::new(CFGameState::default()))
+    ///     .collect();
+    /// let handle = SearchHandle::spawn_parallel(searches, Budget::Time(Duration::from_millis(50)));
+    /// std::thread::sleep(Duration::from_millis(60));
+    /// println!("{} iterations", handle.stop().iterations);
+    /// ```
+    ///
+    /// With [`Budget::Iterations`] and searches seeded with [`Search::seed`], each thread runs a
+    /// fixed share of the iterations and the merged result is the same on every run.
+    pub fn spawn_parallel(searches: Vec<Search<T>>, budget: Budget) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let snapshots = Arc::new(Mutex::new(
+            searches.iter().map(Search::snapshot).collect::<Vec<_>>(),
+        ));
+        let end = match budget {
+            Budget::Time(duration) => Some(Instant::now() + duration),
+            _ => None,
+        };
+        let count = searches.len() as u32;
+
+        let threads = searches
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut search)| {
+                let stop = stop.clone();
+                let snapshots = snapshots.clone();
+                let target = match budget {
+                    Budget::Iterations(max) => {
+                        let share = max / count + u32::from((i as u32) < max % count);
+                        Some(search.iterations() + u64::from(share))
+                    }
+                    _ => None,
+                };
+                thread::spawn(move || {
+                    // search at least one batch, so that even a search stopped right away has a
+                    // move, unless this thread's share of an iteration budget is nothing at all
+                    loop {
+                        let batch = target.map_or(256, |t| (t - search.iterations()).min(256));
+                        for _ in 0..batch {
+                            search.iterate();
+                        }
+                        snapshots.lock().unwrap()[i] = search.snapshot();
+                        if stop.load(Ordering::Relaxed)
+                            || end.is_some_and(|e| Instant::now() >= e)
+                            || target.is_some_and(|t| search.iterations() >= t)
+                        {
+                            break;
+                        }
+                    }
+                    search
+                })
+            })
+            .collect();
+
+        Self {
+            stop,
+            snapshots,
+            threads,
+        }
+    }
+
+    /// The most recently published statistics, merged over all threads.
+    pub fn snapshot(&self) -> Snapshot<T::Move> {
+        Snapshot::merge(self.snapshots.lock().unwrap().iter())
+    }
+
+    /// Returns true once the budget has been used up.
+    pub fn is_finished(&self) -> bool {
+        self.threads.iter().all(|t| t.is_finished())
+    }
+
+    /// Stops the search and returns its final statistics.
+    pub fn stop(self) -> Snapshot<T::Move> {
+        let snapshots = self
+            .into_searches()
+            .iter()
+            .map(Search::snapshot)
+            .collect::<Vec<_>>();
+        Snapshot::merge(&snapshots)
+    }
+
+    /// Stops the search and hands back the trees, so that they can be searched further.
+    pub fn into_searches(mut self) -> Vec<Search<T>> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.threads
+            .drain(..)
+            .map(|thread| thread.join().expect("search thread panicked"))
+            .collect()
+    }
+}
+
+impl<T: GameState> Drop for SearchHandle<T> {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect_four::{CFGameState, CFMove, Position};
+
+    fn play(cols: &[usize]) -> CFGameState {
+        cols.iter().fold(CFGameState::default(), |state, &col| {
+            let color = state.next_player();
+            state.apply_move(CFMove { color, col })
+        })
+    }
+
+    #[test]
+    fn finds_immediate_win() {
+        let mut search = Search::new(play(&[0, 1, 0, 1, 0, 1]));
+        search.run_iterations(2000);
+
+        let best = search.best().unwrap();
+        assert_eq!(best.action.col, 0);
+        assert!(best.win_rate > 0.9);
+        assert_eq!(search.iterations(), 2000);
+    }
+
+    #[test]
+    fn background_search() {
+        let handle = SearchHandle::spawn(
+            Search::new(play(&[0, 1, 0, 1, 0, 1])),
+            Budget::Time(Duration::from_millis(20)),
+        );
+        while !handle.is_finished() {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let snapshot = handle.stop();
+        assert!(snapshot.iterations >= 256);
+        assert_eq!(snapshot.best().unwrap().action.col, 0);
+
+        let handle = SearchHandle::spawn(Search::new(CFGameState::default()), Budget::Infinite);
+        thread::sleep(Duration::from_millis(5));
+        assert!(!handle.is_finished());
+        assert!(handle.stop().iterations > 0);
+    }
+
+    #[test]
+    fn root_visits_add_up() {
+        let mut search = Search::new(CFGameState::default());
+        search.run_iterations(500);
+
+        let stats = search.root_stats();
+        assert_eq!(stats.len(), 7);
+        // the first iteration already expands the root
+        assert_eq!(stats.iter().map(|s| s.visits).sum::<u64>(), 500);
+
+        let mut search = Search::new(CFGameState::default());
+        search.run_iterations(1);
+        assert_eq!(search.best().unwrap().visits, 1);
+    }
+
+    #[test]
+    fn node_limit() {
+        let mut search = Search::new(CFGameState::default()).max_nodes(100).seed(1);
+        search.run_iterations(2000);
+        // the last expansion may add a few children beyond the limit
+        assert!(search.nodes.len() < 100 + 7);
+        let stats = search.root_stats();
+        assert_eq!(stats.iter().map(|s| s.visits).sum::<u64>(), 2000);
+
+        // the root is expanded even without room for its children
+        let mut search = Search::new(CFGameState::default()).max_nodes(1);
+        search.run_iterations(10);
+        assert_eq!(search.nodes.len(), 8);
+        assert!(search.best().is_some());
+    }
+
+    #[test]
+    fn leaf_evaluation() {
+        let evaluator = crate::eval::Evaluator::default();
+        let mut search = Search::new(play(&[0, 1, 0, 1, 0, 1]))
+            .evaluate_leaves(move |state| evaluator.win_probability(state));
+        search.run_iterations(500);
+        assert_eq!(search.best().unwrap().action.col, 0);
+    }
+
+    #[test]
+    fn parallel_search() {
+        let searches = (0..3)
+            .map(|_| Search::new(play(&[0, 1, 0, 1, 0, 1])))
+            .collect();
+        let handle =
+            SearchHandle::spawn_parallel(searches, Budget::Time(Duration::from_millis(20)));
+        while !handle.is_finished() {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let snapshot = handle.stop();
+        assert!(snapshot.iterations >= 3 * 256);
+        assert_eq!(snapshot.stats.len(), 7);
+        assert_eq!(snapshot.best().unwrap().action.col, 0);
+
+        // fewer iterations than threads still rate a move
+        let searches = (0..8)
+            .map(|_| Search::new(CFGameState::default()))
+            .collect();
+        let snapshot = SearchHandle::spawn_parallel(searches, Budget::Iterations(4)).stop();
+        assert_eq!(snapshot.iterations, 4);
+        assert!(snapshot.best().is_some());
+    }
+
+    #[test]
+    fn merge_snapshots() {
+        let stats = |col, visits, win_rate| MoveStats {
+            action: col,
+            visits,
+            win_rate,
+            proven: None,
+        };
+        let a = Snapshot {
+            iterations: 10,
+            stats: vec![stats(0, 6, 0.5), stats(1, 3, 0.0)],
+            pv: vec![0, 1],
+        };
+        let b = Snapshot {
+            iterations: 20,
+            stats: vec![stats(1, 12, 0.75), stats(2, 7, 1.0)],
+            pv: vec![1, 2, 3],
+        };
+
+        let merged = Snapshot::merge([&a, &b]);
+        assert_eq!(merged.iterations, 30);
+        assert_eq!(merged.stats.len(), 3);
+        assert_eq!(merged.stats[1].visits, 15);
+        assert_eq!(merged.stats[1].win_rate, 0.6);
+        assert_eq!(merged.best().unwrap().action, 1);
+        assert_eq!(merged.pv, [1, 2, 3]);
+    }
+
+    #[test]
+    fn reuse_tree() {
+        let mut search = Search::new(CFGameState::default());
+        search.run_iterations(2000);
+
+        let reply = search
+            .root_stats()
+            .into_iter()
+            .max_by_key(|s| s.visits)
+            .unwrap();
+        let kept = search.advance(reply.action);
+        assert_eq!(kept, reply.visits);
+        assert_eq!(search.iterations(), 0);
+
+        // the statistics of the subtree carry over, and the tree keeps growing from there
+        let visits = search.root_stats().iter().map(|s| s.visits).sum::<u64>();
+        assert_eq!(visits, kept - 1);
+        search.run_iterations(100);
+        let visits_after = search.root_stats().iter().map(|s| s.visits).sum::<u64>();
+        assert_eq!(visits_after, visits + 100);
+        assert!(search
+            .root_stats()
+            .iter()
+            .all(|s| s.action.color == Position::Black));
+
+        // an unexplored move starts over
+        let mut search = Search::new(CFGameState::default());
+        let action = CFMove {
+            color: Position::Red,
+            col: 3,
+        };
+        assert_eq!(search.advance(action), 0);
+        assert!(search.root_stats().is_empty());
+    }
+
+    #[test]
+    fn searches_come_back() {
+        let handle = SearchHandle::spawn(
+            Search::new(CFGameState::default()),
+            Budget::Time(Duration::from_millis(10)),
+        );
+        while !handle.is_finished() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        let searches = handle.into_searches();
+        assert_eq!(searches.len(), 1);
+        assert!(searches[0].iterations() > 0);
+    }
+
+    #[test]
+    fn seeded_searches_repeat() {
+        let search = |seed| {
+            let mut search = Search::new(CFGameState::default()).seed(seed);
+            search.run_iterations(1000);
+            search.snapshot()
+        };
+        assert_eq!(search(7), search(7));
+        assert_ne!(search(7), search(8));
+
+        // each thread gets its share of the iterations, however the threads are scheduled
+        let parallel = || {
+            let searches = (0..3)
+                .map(|i| Search::new(CFGameState::default()).seed(i))
+                .collect();
+            let handle = SearchHandle::spawn_parallel(searches, Budget::Iterations(1000));
+            while !handle.is_finished() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            handle.stop()
+        };
+        let snapshot = parallel();
+        assert_eq!(snapshot.iterations, 1000);
+        assert_eq!(snapshot, parallel());
+    }
+
+    #[test]
+    fn seeded_games_replay() {
+        let play_game = || {
+            let mut search = Search::new(CFGameState::default()).seed(42);
+            let mut moves = Vec::new();
+            loop {
+                let handle = SearchHandle::spawn(search, Budget::Iterations(300));
+                while !handle.is_finished() {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                search = handle.into_searches().pop().unwrap();
+                let action = search.best().unwrap().action;
+                moves.push(action.col);
+                search.advance(action);
+                if search.nodes[0].state.is_terminal_state().is_some() {
+                    return moves;
+                }
+            }
+        };
+        assert_eq!(play_game(), play_game());
+    }
+
+    #[test]
+    fn move_selection() {
+        let stats = |col, visits, win_rate| MoveStats {
+            action: col,
+            visits,
+            win_rate,
+            proven: None,
+        };
+        let snapshot = Snapshot {
+            iterations: 19,
+            stats: vec![
+                stats(0, 10, 0.5),
+                stats(1, 8, 0.75),
+                stats(2, 1, 1.0),
+                stats(3, 0, 0.0),
+            ],
+            pv: Vec::new(),
+        };
+        let select = |selection| snapshot.select(selection).unwrap().action;
+        assert_eq!(select(MoveSelection::MostVisits), 0);
+        assert_eq!(select(MoveSelection::HighestValue), 2);
+        assert_eq!(select(MoveSelection::RobustMax), 1);
+
+        let snapshot = Snapshot {
+            iterations: 15,
+            stats: vec![stats(0, 5, 0.6), stats(1, 10, 0.8)],
+            pv: Vec::new(),
+        };
+        assert_eq!(snapshot.select(MoveSelection::RobustMax).unwrap().action, 1);
+
+        // proofs come before the statistics
+        let mut snapshot = snapshot;
+        snapshot.stats[0].proven = Some(Outcome::Loss);
+        assert_eq!(
+            snapshot.select(MoveSelection::MostVisits).unwrap().action,
+            1
+        );
+        snapshot.stats[0].proven = Some(Outcome::Win);
+        assert_eq!(
+            snapshot.select(MoveSelection::MostVisits).unwrap().action,
+            0
+        );
+
+        assert_eq!("robust-max".parse(), Ok(MoveSelection::RobustMax));
+        assert!("max".parse::<MoveSelection>().is_err());
+    }
+
+    #[test]
+    fn draw_value() {
+        // only column 4 is left, and filling it draws
+        let state = crate::notation::parse("5471256622612712662157437715763153533344").unwrap();
+
+        let mut search = Search::new(state.clone());
+        search.run_iterations(10);
+        assert_eq!(search.best().unwrap().win_rate, 0.0);
+
+        let mut search = Search::new(state).draw_value(0.5);
+        search.run_iterations(10);
+        assert_eq!(search.best().unwrap().win_rate, 0.5);
+    }
+
+    #[test]
+    fn solver() {
+        let mut search = Search::new(play(&[0, 1, 0, 1, 0, 1]));
+        search.run_iterations(100);
+        let best = search.best().unwrap();
+        assert_eq!(best.action.col, 0);
+        assert_eq!(best.proven, Some(Outcome::Win));
+        assert_eq!(search.snapshot().forced_win().unwrap().action.col, 0);
+
+        // Black threatens both ends of three in a row, so every move of Red loses
+        let mut search = Search::new(play(&[0, 2, 0, 3, 6, 4]));
+        search.run_iterations(3000);
+        let stats = search.root_stats();
+        assert_eq!(stats.len(), 7);
+        assert!(stats.iter().all(|s| s.proven == Some(Outcome::Loss)));
+
+        let mut search = Search::new(play(&[0, 1, 0, 1, 0, 1])).solver(false);
+        search.run_iterations(100);
+        assert!(search.root_stats().iter().all(|s| s.proven.is_none()));
+    }
+
+    #[test]
+    fn principal_variation() {
+        // the forced win ends the line
+        let mut search = Search::new(play(&[0, 1, 0, 1, 0, 1]));
+        search.run_iterations(100);
+        let pv = search.principal_variation();
+        assert_eq!(pv.len(), 1);
+        assert_eq!(pv[0].col, 0);
+
+        // every move of Red loses, and the line shows how
+        let state = play(&[0, 2, 0, 3, 6, 4]);
+        let mut search = Search::new(state.clone());
+        search.run_iterations(3000);
+        let end = search
+            .principal_variation()
+            .into_iter()
+            .fold(state, |state, action| state.apply_move(action));
+        assert_eq!(end.is_terminal_state(), Some(Position::Black));
+
+        let mut search = Search::new(CFGameState::default()).seed(5);
+        search.run_iterations(2000);
+        let snapshot = search.snapshot();
+        assert!(snapshot.pv.len() > 2);
+        assert_eq!(snapshot.pv[0], snapshot.best().unwrap().action);
+        let mut state = CFGameState::default();
+        for action in snapshot.pv {
+            assert_eq!(action.color, state.next_player());
+            assert!(state.can_play(action.col));
+            state = state.apply_move(action);
+        }
+    }
+
+    #[test]
+    fn rave() {
+        let mut search = Search::new(play(&[0, 1, 0, 1, 0, 1])).rave(1000.0);
+        search.run_iterations(500);
+        assert_eq!(search.best().unwrap().action.col, 0);
+
+        let mut search = Search::new(CFGameState::default()).rave(1000.0);
+        search.run_iterations(500);
+        // every visit of a move from the root is also an AMAF visit
+        for &c in &search.nodes[0].children {
+            let child = &search.nodes[c];
+            assert!(child.amaf_n >= child.n);
+            assert!(child.amaf_w <= child.amaf_n as f64);
+        }
+        let amaf = search.nodes[0]
+            .children
+            .iter()
+            .map(|&c| search.nodes[c].amaf_n);
+        assert!(amaf.sum::<u64>() > 499);
+    }
+
+    #[test]
+    fn trees() {
+        let mut a = Search::new(CFGameState::default()).seed(1);
+        a.run_iterations(1000);
+        let mut b = Search::new(CFGameState::default()).seed(2);
+        b.run_iterations(500);
+
+        let tree = a.tree(2);
+        assert_eq!(tree.visits, 1000);
+        assert_eq!(tree.children.len(), 7);
+        assert!(tree
+            .children
+            .iter()
+            .all(|c| c.children.len() == 7 || c.visits < 2));
+        assert!(tree.children[0]
+            .children
+            .iter()
+            .all(|c| c.children.is_empty()));
+
+        let mut merged = a.tree(1);
+        merged.merge(b.tree(1));
+        assert_eq!(merged.visits, 1500);
+        assert_eq!(merged.size(), 8);
+
+        merged.prune(200);
+        assert!(merged.children.iter().all(|c| c.visits >= 200));
+        assert!(merged
+            .children
+            .windows(2)
+            .all(|w| w[0].visits >= w[1].visits));
+    }
+}
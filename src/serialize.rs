@@ -0,0 +1,191 @@
+//! `serde` support for the game types.
+//!
+//! Moves are written as `{"color": "red", "col": 4}` with columns counted from 1, and positions
+//! as their rows from top to bottom with `R` for Red, `B` for Black and `.` for empty cells:
+//!
+//! ```json
+//! {
+//!   "board": [".......", ".......", ".......", ".......", "...B...", "...RR.."],
+//!   "next_player": "black"
+//! }
+//! ```
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::connect_four::{CFGameState, CFMove, Position};
+
+#[derive(Serialize, Deserialize)]
+struct MoveRepr {
+    color: Position,
+    col: usize,
+}
+
+impl Serialize for CFMove {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        MoveRepr {
+            color: self.color,
+            col: self.col + 1,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CFMove {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = MoveRepr::deserialize(deserializer)?;
+        if repr.color == Position::Empty {
+            return Err(de::Error::custom("a move must be made by red or black"));
+        }
+        if !(1..=7).contains(&repr.col) {
+            return Err(de::Error::custom(format!("invalid column {}", repr.col)));
+        }
+        Ok(CFMove {
+            color: repr.color,
+            col: repr.col - 1,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StateRepr {
+    board: Vec<String>,
+    next_player: Position,
+}
+
+impl Serialize for CFGameState {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        StateRepr {
+            board: self.rows().into(),
+            next_player: self.next_player(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CFGameState {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = StateRepr::deserialize(deserializer)?;
+        if repr.board.len() != 6 {
+            return Err(de::Error::custom("the board must have 6 rows"));
+        }
+
+        let mut board = [Position::Empty; 7 * 6];
+        for (row, line) in repr.board.iter().enumerate() {
+            if line.chars().count() != 7 {
+                return Err(de::Error::custom(format!(
+                    "row {} must have 7 cells",
+                    row + 1
+                )));
+            }
+            for (col, c) in line.chars().enumerate() {
+                board[row * 7 + col] = match c {
+                    'R' => Position::Red,
+                    'B' => Position::Black,
+                    '.' => Position::Empty,
+                    _ => return Err(de::Error::custom(format!("invalid cell '{c}'"))),
+                };
+            }
+        }
+
+        CFGameState::from_cells(board, repr.next_player).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use yamcts::GameState;
+
+    use crate::{notation, record::GameRecord};
+
+    use super::*;
+
+    #[test]
+    fn moves() {
+        let action = CFMove {
+            color: Position::Black,
+            col: 3,
+        };
+        let value = serde_json::to_value(action).unwrap();
+        assert_eq!(value, json!({ "color": "black", "col": 4 }));
+        assert_eq!(serde_json::from_value::<CFMove>(value).unwrap(), action);
+
+        assert!(serde_json::from_value::<CFMove>(json!({ "color": "red", "col": 0 })).is_err());
+        assert!(serde_json::from_value::<CFMove>(json!({ "color": "empty", "col": 1 })).is_err());
+    }
+
+    #[test]
+    fn states() {
+        let state = notation::parse("4454").unwrap();
+        let value = serde_json::to_value(&state).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "board": [".......", ".......", ".......", "...B...", "...B...", "...RR.."],
+                "next_player": "red",
+            })
+        );
+
+        let parsed = serde_json::from_value::<CFGameState>(value).unwrap();
+        assert_eq!(parsed.next_player(), Position::Red);
+        for col in 0..7 {
+            for row in 0..6 {
+                assert_eq!(parsed.pos(col, row), state.pos(col, row));
+            }
+        }
+
+        // the round trip keeps playing the same game
+        let won = notation::play_cols(&parsed, [0, 3, 1, 3]).unwrap();
+        assert_eq!(won.is_terminal_state(), Some(Position::Black));
+    }
+
+    #[test]
+    fn invalid_states() {
+        let parse = |board: [&str; 6]| {
+            serde_json::from_value::<CFGameState>(json!({ "board": board, "next_player": "red" }))
+        };
+        let empty = ".......";
+
+        assert!(parse([empty, empty, empty, empty, empty, "R.B...."]).is_ok());
+        assert!(parse([empty, empty, empty, empty, "R......", "B......"]).is_ok());
+        assert!(parse([empty, empty, empty, empty, "R......", empty]).is_err());
+        assert!(parse([empty, empty, empty, empty, empty, "RR....."]).is_err());
+        assert!(parse([empty, empty, empty, empty, empty, "RX....."]).is_err());
+        assert!(parse([empty, empty, empty, empty, empty, "R......."]).is_err());
+
+        // the side to move must follow from the discs, with Red moving first
+        let parse_for = |board: [&str; 6], next_player: &str| {
+            serde_json::from_value::<CFGameState>(
+                json!({ "board": board, "next_player": next_player }),
+            )
+        };
+        assert!(parse_for([empty, empty, empty, empty, empty, "...B..."], "red").is_err());
+        assert!(parse_for([empty, empty, empty, empty, empty, "...B..."], "black").is_err());
+        assert!(parse_for([empty, empty, empty, empty, empty, "...R..."], "red").is_err());
+        assert!(parse_for([empty, empty, empty, empty, empty, "...R..."], "black").is_ok());
+        assert!(parse_for([empty, empty, empty, empty, empty, "..RB..."], "black").is_err());
+        assert!(parse_for([empty; 6], "black").is_err());
+        assert!(parse_for([empty; 6], "empty").is_err());
+
+        // only the player who moved last can have won
+        let four = [empty, empty, "R......", "R......", "R......", "RBBB..."];
+        assert!(parse_for(four, "black").is_ok());
+        let both = [empty, empty, "RB.....", "RB.....", "RB.....", "RB....."];
+        assert!(parse_for(both, "red").is_err());
+    }
+
+    #[test]
+    fn records() {
+        let mut record = GameRecord::from_notation("1212121").unwrap();
+        record.red = Some("Alice".into());
+
+        let value = serde_json::to_value(&record).unwrap();
+        assert_eq!(
+            value,
+            json!({ "red": "Alice", "moves": "1212121", "result": "red" })
+        );
+        assert_eq!(serde_json::from_value::<GameRecord>(value).unwrap(), record);
+
+        assert!(serde_json::from_value::<GameRecord>(json!({ "moves": "11111111" })).is_err());
+    }
+}
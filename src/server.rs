@@ -0,0 +1,507 @@
+//! A small HTTP/1.1 server exposing the engine as a JSON API, meant to be run on localhost.
+//!
+//! | method | path | |
+//! | --- | --- | --- |
+//! | `POST` | `/games` | create a new game |
+//! | `GET` | `/games/{id}` | fetch a game |
+//! | `POST` | `/games/{id}/moves` | play `{"col": 4}` for the side to move |
+//! | `POST` | `/games/{id}/ai?movetime=1000` | let the AI play a move |
+//! | `GET` | `/games/{id}/analysis?movetime=500` | search the position without playing |
+//!
+//! The AI endpoints accept either `movetime` in milliseconds or `iterations` as their budget,
+//! capped at a minute and a million iterations. The search tree is capped at a million positions,
+//! which bounds the memory a request can use.
+//! Columns are numbered 1 to 7 from the left, as in the engine protocol.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use log::{info, warn};
+use serde_json::{json, Value};
+use yamcts::GameState;
+
+use crate::{
+    connect_four::{CFGameState, CFMove},
+    record::GameResult,
+    search::Search,
+};
+
+const DEFAULT_MOVETIME_MS: u64 = 1000;
+const MAX_MOVETIME_MS: u64 = 60_000;
+const MAX_ITERATIONS: u64 = 1_000_000;
+/// The most positions the tree of one request holds, at about 200 bytes each.
+const MAX_TREE_NODES: usize = 1_000_000;
+const MAX_BODY_LEN: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Default)]
+struct Game {
+    state: CFGameState,
+    moves: Vec<CFMove>,
+}
+
+#[derive(Default)]
+struct Games {
+    next_id: u64,
+    games: HashMap<u64, Game>,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct HttpError {
+    status: u16,
+    message: String,
+}
+
+impl HttpError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+/// How long an AI request may search, from its query string.
+enum RequestBudget {
+    MoveTime(Duration),
+    Iterations(u32),
+}
+
+/// The HTTP server, holding all games in memory.
+pub struct Server {
+    listener: TcpListener,
+    games: Arc<Mutex<Games>>,
+}
+
+impl Server {
+    /// Listens on `addr` without accepting connections yet.
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            games: Arc::default(),
+        })
+    }
+
+    /// The address being listened on.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts connections forever, handling each one on its own thread.
+    pub fn run(self) -> io::Result<()> {
+        info!("listening on http://{}", self.local_addr()?);
+        for stream in self.listener.incoming() {
+            let stream = stream?;
+            let games = self.games.clone();
+            thread::spawn(move || {
+                if let Err(err) = handle_connection(stream, &games) {
+                    warn!("connection error: {err}");
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+fn handle_connection(stream: TcpStream, games: &Mutex<Games>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let (status, body) = match read_request(&mut reader)? {
+        Some(request) => {
+            info!("{} {}", request.method, request.path);
+            match route(&request, games) {
+                Ok((status, body)) => (status, body),
+                Err(err) => (err.status, json!({ "error": err.message })),
+            }
+        }
+        None => (400, json!({ "error": "malformed request" })),
+    };
+
+    write_response(stream, status, &body)
+}
+
+fn read_request(reader: &mut impl BufRead) -> io::Result<Option<Request>> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Ok(None);
+    };
+    let method = method.to_string();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let path = path.to_string();
+    let query = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                match value.trim().parse() {
+                    Ok(len) if len <= MAX_BODY_LEN => content_length = len,
+                    _ => return Ok(None),
+                }
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Some(Request {
+        method,
+        path,
+        query,
+        body,
+    }))
+}
+
+fn write_response(mut stream: TcpStream, status: u16, body: &Value) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        409 => "Conflict",
+        _ => "Error",
+    };
+    let body = body.to_string();
+
+    write!(
+        stream,
+        "HTTP/1.1 {status} {reason}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+fn route(request: &Request, games: &Mutex<Games>) -> Result<(u16, Value), HttpError> {
+    let segments = request
+        .path
+        .trim_matches('/')
+        .split('/')
+        .collect::<Vec<_>>();
+
+    match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["games"]) => {
+            let mut games = games.lock().unwrap();
+            games.next_id += 1;
+            let id = games.next_id;
+            let game = Game::default();
+            let body = game_json(id, &game);
+            games.games.insert(id, game);
+            Ok((201, body))
+        }
+        ("GET", ["games", id]) => {
+            let id = parse_id(id)?;
+            let game = find_game(games, id)?;
+            Ok((200, game_json(id, &game)))
+        }
+        ("POST", ["games", id, "moves"]) => {
+            let id = parse_id(id)?;
+            let col = serde_json::from_slice::<Value>(&request.body)
+                .ok()
+                .and_then(|body| body.get("col")?.as_u64())
+                .ok_or_else(|| HttpError::new(400, "expected a body like {\"col\": 4}"))?;
+
+            let mut games = games.lock().unwrap();
+            let game = games
+                .games
+                .get_mut(&id)
+                .ok_or_else(|| HttpError::new(404, "no such game"))?;
+            play(game, col as usize)?;
+            Ok((200, game_json(id, game)))
+        }
+        ("POST", ["games", id, "ai"]) => {
+            let id = parse_id(id)?;
+            let budget = parse_budget(&request.query)?;
+            let game = find_game(games, id)?;
+            let search = analyse(&game, budget)?;
+            let best = search
+                .best()
+                .ok_or_else(|| HttpError::new(400, "no move was searched"))?;
+
+            let mut games = games.lock().unwrap();
+            let current = games
+                .games
+                .get_mut(&id)
+                .ok_or_else(|| HttpError::new(404, "no such game"))?;
+            if current.moves.len() != game.moves.len() {
+                return Err(HttpError::new(409, "the game changed while searching"));
+            }
+            play(current, best.action.col + 1)?;
+
+            Ok((
+                200,
+                json!({
+                    "move": move_json(&best.action),
+                    "iterations": search.iterations(),
+                    "winrate": best.win_rate,
+                    "game": game_json(id, current),
+                }),
+            ))
+        }
+        ("GET", ["games", id, "analysis"]) => {
+            let id = parse_id(id)?;
+            let budget = parse_budget(&request.query)?;
+            let game = find_game(games, id)?;
+            let search = analyse(&game, budget)?;
+
+            let mut stats = search.root_stats();
+            stats.sort_by_key(|s| s.action.col);
+            let moves = stats
+                .iter()
+                .map(|s| {
+                    json!({
+                        "col": s.action.col + 1,
+                        "visits": s.visits,
+                        "winrate": s.win_rate,
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            Ok((
+                200,
+                json!({
+                    "iterations": search.iterations(),
+                    "best": search.best().map(|s| s.action.col + 1),
+                    "pv": search
+                        .principal_variation()
+                        .iter()
+                        .map(|action| action.col + 1)
+                        .collect::<Vec<_>>(),
+                    "moves": moves,
+                }),
+            ))
+        }
+        _ => Err(HttpError::new(404, "not found")),
+    }
+}
+
+fn parse_id(id: &str) -> Result<u64, HttpError> {
+    id.parse().map_err(|_| HttpError::new(404, "no such game"))
+}
+
+fn find_game(games: &Mutex<Games>, id: u64) -> Result<Game, HttpError> {
+    games
+        .lock()
+        .unwrap()
+        .games
+        .get(&id)
+        .cloned()
+        .ok_or_else(|| HttpError::new(404, "no such game"))
+}
+
+fn parse_budget(query: &HashMap<String, String>) -> Result<RequestBudget, HttpError> {
+    let number = |key: &str| {
+        query
+            .get(key)
+            .map(|v| {
+                v.parse::<u64>()
+                    .map_err(|_| HttpError::new(400, format!("invalid {key} '{v}'")))
+            })
+            .transpose()
+    };
+
+    if let Some(iterations) = number("iterations")? {
+        return Ok(RequestBudget::Iterations(
+            iterations.min(MAX_ITERATIONS) as u32
+        ));
+    }
+
+    let ms = number("movetime")?.unwrap_or(DEFAULT_MOVETIME_MS);
+    Ok(RequestBudget::MoveTime(Duration::from_millis(
+        ms.min(MAX_MOVETIME_MS),
+    )))
+}
+
+/// Plays the 1-based column `col` for the side to move.
+fn play(game: &mut Game, col: usize) -> Result<(), HttpError> {
+    if game.state.is_terminal_state().is_some() {
+        return Err(HttpError::new(400, "the game is already over"));
+    }
+
+    let col = col
+        .checked_sub(1)
+        .filter(|&col| game.state.can_play(col))
+        .ok_or_else(|| HttpError::new(400, format!("illegal move {col}")))?;
+
+    let action = CFMove {
+        color: game.state.next_player(),
+        col,
+    };
+    game.state = game.state.apply_move(action);
+    game.moves.push(action);
+    Ok(())
+}
+
+fn analyse(game: &Game, budget: RequestBudget) -> Result<Search<CFGameState>, HttpError> {
+    if game.state.is_terminal_state().is_some() {
+        return Err(HttpError::new(400, "the game is already over"));
+    }
+
+    let mut search = Search::new(game.state.clone()).max_nodes(MAX_TREE_NODES);
+    match budget {
+        RequestBudget::MoveTime(duration) => search.run_for(duration),
+        RequestBudget::Iterations(iterations) => search.run_iterations(iterations),
+    }
+    // even a zero budget rates the moves from the root, which one iteration does
+    if search.iterations() == 0 {
+        search.run_iterations(1);
+    }
+    Ok(search)
+}
+
+fn move_json(action: &CFMove) -> Value {
+    serde_json::to_value(action).unwrap()
+}
+
+fn state_json(state: &CFGameState) -> Value {
+    let result = state.is_terminal_state().map(GameResult::from_terminal);
+    let legal_moves = match result {
+        Some(_) => Vec::new(),
+        None => state.all_moves().iter().map(|m| m.col + 1).collect(),
+    };
+
+    let mut value = serde_json::to_value(state).unwrap();
+    value["legal_moves"] = json!(legal_moves);
+    value["result"] = json!(result);
+    value
+}
+
+fn game_json(id: u64, game: &Game) -> Value {
+    json!({
+        "id": id,
+        "moves": game.moves.iter().map(move_json).collect::<Vec<_>>(),
+        "state": state_json(&game.state),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn start() -> SocketAddr {
+        let server = Server::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+        addr
+    }
+
+    fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, Value) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{method} {path} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let status = response[9..12].parse().unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn create_and_play() {
+        let addr = start();
+
+        let (status, game) = request(addr, "POST", "/games", "");
+        assert_eq!(status, 201);
+        let id = game["id"].as_u64().unwrap();
+        assert_eq!(game["state"]["next_player"], "red");
+
+        let path = format!("/games/{id}/moves");
+        request(addr, "POST", &path, r#"{"col": 4}"#);
+        let (status, game) = request(addr, "POST", &path, r#"{"col": 4}"#);
+        assert_eq!(status, 200);
+        assert_eq!(game["state"]["board"][5], "...R...");
+        assert_eq!(game["state"]["board"][4], "...B...");
+        assert_eq!(game["moves"][1], json!({ "col": 4, "color": "black" }));
+
+        let (_, fetched) = request(addr, "GET", &format!("/games/{id}"), "");
+        assert_eq!(fetched, game);
+    }
+
+    #[test]
+    fn rejects_bad_requests() {
+        let addr = start();
+        let (_, game) = request(addr, "POST", "/games", "");
+        let path = format!("/games/{}/moves", game["id"]);
+
+        let (status, body) = request(addr, "POST", &path, r#"{"col": 8}"#);
+        assert_eq!(status, 400);
+        assert_eq!(body["error"], "illegal move 8");
+
+        let (status, _) = request(addr, "POST", &path, "not json");
+        assert_eq!(status, 400);
+
+        let (status, _) = request(addr, "GET", "/games/999", "");
+        assert_eq!(status, 404);
+    }
+
+    #[test]
+    fn ai_move_and_analysis() {
+        let addr = start();
+        let (_, game) = request(addr, "POST", "/games", "");
+        let id = game["id"].as_u64().unwrap();
+        for col in [1, 2, 1, 2, 1, 2] {
+            let body = format!(r#"{{"col": {col}}}"#);
+            request(addr, "POST", &format!("/games/{id}/moves"), &body);
+        }
+
+        let (status, analysis) = request(
+            addr,
+            "GET",
+            &format!("/games/{id}/analysis?iterations=3000"),
+            "",
+        );
+        assert_eq!(status, 200);
+        assert_eq!(analysis["best"], 1);
+        assert_eq!(analysis["pv"][0], 1);
+        assert_eq!(analysis["moves"].as_array().unwrap().len(), 7);
+
+        let (status, result) =
+            request(addr, "POST", &format!("/games/{id}/ai?iterations=3000"), "");
+        assert_eq!(status, 200);
+        assert_eq!(result["move"], json!({ "col": 1, "color": "red" }));
+        assert_eq!(result["game"]["state"]["result"], "red");
+        assert_eq!(result["game"]["state"]["legal_moves"], json!([]));
+
+        // the smallest budgets still play a legal move
+        for budget in ["iterations=1", "iterations=0", "movetime=0"] {
+            let (_, game) = request(addr, "POST", "/games", "");
+            let id = game["id"].as_u64().unwrap();
+            let (status, result) = request(addr, "POST", &format!("/games/{id}/ai?{budget}"), "");
+            assert_eq!(status, 200, "{budget}");
+            assert_eq!(result["game"]["moves"].as_array().unwrap().len(), 1);
+        }
+    }
+}
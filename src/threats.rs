@@ -0,0 +1,118 @@
+//! Tactical information about a position: where each player can complete four in a row, and
+//! which columns are dangerous to play.
+//!
+//! Rows in a [`Threat`] count from 0 at the top like everywhere else, but odd and even refer to
+//! rows counted from 1 at the bottom, as in Victor Allis's analysis of the game. When the board
+//! fills up column by column, Red as the first player tends to get the odd rows and Black the
+//! even ones, so an odd threat is the one that usually matters for Red and an even threat the
+//! one that matters for Black.
+
+use crate::connect_four::{CFGameState, Position};
+
+/// An empty cell where a player would complete four in a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Threat {
+    /// The player who would win there.
+    pub color: Position,
+    /// The column counted from 0 on the left.
+    pub col: usize,
+    /// The row counted from 0 at the top.
+    pub row: usize,
+}
+
+impl Threat {
+    /// The row counted from 1 at the bottom, as used for odd and even threats.
+    pub fn row_from_bottom(&self) -> usize {
+        6 - self.row
+    }
+
+    /// Returns true for a threat on the first, third or fifth row from the bottom.
+    pub fn is_odd(&self) -> bool {
+        self.row_from_bottom() % 2 == 1
+    }
+}
+
+impl CFGameState {
+    /// The columns where `color` wins with its next disc.
+    pub fn winning_cols(&self, color: Position) -> Vec<usize> {
+        (0..7)
+            .filter(|&col| {
+                self.landing_row(col)
+                    .is_some_and(|row| self.completes_four(col, row, color))
+            })
+            .collect()
+    }
+
+    /// Every empty cell where `color` would complete four in a row, whether it can be played
+    /// right away or not, from left to right and top to bottom within a column.
+    pub fn threats(&self, color: Position) -> Vec<Threat> {
+        (0..7)
+            .flat_map(|col| (0..6).map(move |row| (col, row)))
+            .filter(|&(col, row)| {
+                self.pos(col, row) == Position::Empty && self.completes_four(col, row, color)
+            })
+            .map(|(col, row)| Threat { color, col, row })
+            .collect()
+    }
+
+    /// The columns where a disc of `color` would let the opponent win by playing on top of it.
+    pub fn poisoned_cols(&self, color: Position) -> Vec<usize> {
+        let opponent = color.opponent();
+        (0..7)
+            .filter(|&col| {
+                self.landing_row(col)
+                    .is_some_and(|row| row > 0 && self.completes_four(col, row - 1, opponent))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation;
+
+    #[test]
+    fn immediate_wins() {
+        let state = notation::parse("121212").unwrap();
+        assert_eq!(state.winning_cols(Position::Red), [0]);
+        assert_eq!(state.winning_cols(Position::Black), [1]);
+
+        // Red has an open three along the bottom
+        let state = notation::parse("27374").unwrap();
+        assert_eq!(state.winning_cols(Position::Red), [0, 4]);
+        assert!(state.winning_cols(Position::Black).is_empty());
+    }
+
+    #[test]
+    fn odd_and_even_threats() {
+        // Black has three in the second row from the bottom, Red three along the bottom with
+        // the fourth cell taken
+        let state = notation::parse("11337272").unwrap();
+        let threats = state.threats(Position::Black);
+        assert_eq!(
+            threats,
+            [Threat {
+                color: Position::Black,
+                col: 3,
+                row: 4
+            }]
+        );
+        assert_eq!(threats[0].row_from_bottom(), 2);
+        assert!(!threats[0].is_odd());
+
+        // the cell is not playable yet, so it is not an immediate win
+        assert!(state.winning_cols(Position::Black).is_empty());
+        assert!(state.threats(Position::Red).is_empty());
+
+        let state = notation::parse("27374").unwrap();
+        assert!(state.threats(Position::Red).iter().all(Threat::is_odd));
+    }
+
+    #[test]
+    fn poisoned_columns() {
+        let state = notation::parse("11337272").unwrap();
+        assert_eq!(state.poisoned_cols(Position::Red), [3]);
+        assert!(state.poisoned_cols(Position::Black).is_empty());
+    }
+}
@@ -0,0 +1,290 @@
+//! Spectator mode where two AI configurations play against each other.
+
+use std::time::{Duration, Instant};
+
+use ratatui::{style::Stylize, text::Line};
+
+use connect_four_mcts::{
+    connect_four::{CFGameState, CFMove, Position},
+    search::{Budget, MoveSelection, Outcome, Search, SearchConfig, SearchHandle, Snapshot},
+    GameState,
+};
+
+use crate::{disc_style, player_name, pv_text};
+
+/// The most positions the trees of one AI hold between its threads, at about 200 bytes each.
+const MAX_TREE_NODES: usize = 1_000_000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct AiConfig {
+    pub budget: Budget,
+    pub selection: MoveSelection,
+    pub threads: usize,
+    /// How each thread searches. A seed makes the AI repeat its moves when it has an iteration
+    /// budget.
+    pub search: SearchConfig,
+}
+
+impl AiConfig {
+    /// New searches of `state`, one per thread, which share the node limit between them.
+    pub fn searches(&self, state: &CFGameState) -> Vec<Search<CFGameState>> {
+        (0..self.threads)
+            .map(|i| {
+                self.search
+                    .search(state.clone(), i)
+                    .max_nodes(MAX_TREE_NODES / self.threads)
+            })
+            .collect()
+    }
+
+    fn search(&self, state: CFGameState) -> SearchHandle<CFGameState> {
+        SearchHandle::spawn_parallel(self.searches(&state), self.budget)
+    }
+
+    fn budget_label(&self) -> String {
+        match self.budget {
+            Budget::Time(duration) => format!("{:.1}s", duration.as_secs_f64()),
+            Budget::Iterations(iterations) => format!("{iterations} it"),
+            Budget::Infinite => "no limit".to_string(),
+        }
+    }
+}
+
+pub struct Watch {
+    configs: [AiConfig; 2],
+    delay: Duration,
+    paused: bool,
+    step: bool,
+    search: Option<SearchHandle<CFGameState>>,
+    snapshots: [Option<Snapshot<CFMove>>; 2],
+    /// The trees searched for the last move played.
+    searched: Vec<Search<CFGameState>>,
+    last_move_at: Instant,
+}
+
+fn side(player: Position) -> usize {
+    match player {
+        Position::Red => 0,
+        _ => 1,
+    }
+}
+
+impl Watch {
+    /// `delay` is the least amount of time between two moves, so that they can be followed.
+    pub fn new(red: AiConfig, black: AiConfig, delay: Duration) -> Self {
+        Self {
+            configs: [red, black],
+            delay,
+            paused: false,
+            step: false,
+            search: None,
+            snapshots: [None, None],
+            searched: Vec::new(),
+            last_move_at: Instant::now(),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Plays a single move while paused, as soon as the side to move has finished thinking.
+    pub fn step(&mut self) {
+        self.step = true;
+    }
+
+    /// Keeps the side to move thinking and returns its move once it is due to be played.
+    pub fn poll(&mut self, game: &CFGameState) -> Option<CFMove> {
+        let side = side(game.next_player());
+        let Some(search) = &self.search else {
+            self.snapshots[side] = None;
+            self.search = Some(self.configs[side].search(game.clone()));
+            return None;
+        };
+
+        self.snapshots[side] = Some(search.snapshot());
+
+        let due = self.step || (!self.paused && self.last_move_at.elapsed() >= self.delay);
+        if !due || !search.is_finished() {
+            return None;
+        }
+
+        self.searched = self.search.take().unwrap().into_searches();
+        let snapshots = self
+            .searched
+            .iter()
+            .map(Search::snapshot)
+            .collect::<Vec<_>>();
+        let snapshot = Snapshot::merge(&snapshots);
+        let best = snapshot.select(self.configs[side].selection);
+        self.snapshots[side] = Some(snapshot);
+        self.step = false;
+        self.last_move_at = Instant::now();
+
+        // only a search that never ran has no move to offer
+        Some(best.map_or_else(|| game.all_moves()[0], |best| best.action))
+    }
+
+    /// The trees searched for the last move played, one per thread.
+    pub fn searched(&self) -> &[Search<CFGameState>] {
+        &self.searched
+    }
+
+    /// The line of play expected by the side to move in `game`, as far as it has searched.
+    pub fn pv(&self, game: &CFGameState) -> Vec<CFMove> {
+        self.snapshots[side(game.next_player())]
+            .as_ref()
+            .map_or_else(Vec::new, |s| s.pv.clone())
+    }
+
+    /// The iterations of the last search for `player`.
+    pub fn iterations(&self, player: Position) -> u64 {
+        self.snapshots[side(player)]
+            .as_ref()
+            .map_or(0, |s| s.iterations)
+    }
+
+    /// Describes what each side is thinking, for the analysis panel.
+    pub fn analysis(&self, game: &CFGameState, game_over: bool) -> Vec<Line<'static>> {
+        let mut lines = Vec::new();
+
+        for player in [Position::Red, Position::Black] {
+            let config = &self.configs[side(player)];
+            let mut header = format!(
+                "{}  {}  c={:.2}",
+                player_name(player),
+                config.budget_label(),
+                config.search.exploration_factor
+            );
+            if config.selection != MoveSelection::MostVisits {
+                header += &format!("  {}", config.selection);
+            }
+            if let Some(k) = config.search.rave {
+                header += &format!("  rave={k}");
+            }
+            if !game_over && player == game.next_player() {
+                header += if self.paused {
+                    "  paused"
+                } else {
+                    "  thinking"
+                };
+            }
+            lines.push(Line::from(header).fg(disc_style(player).1).bold());
+
+            let Some(snapshot) = &self.snapshots[side(player)] else {
+                lines.push(Line::from("  waiting"));
+                lines.push(Line::from(""));
+                continue;
+            };
+
+            match snapshot.select(config.selection) {
+                Some(best) if best.proven == Some(Outcome::Win) => lines.push(Line::from(format!(
+                    "  {} iterations, best {}, forced win",
+                    snapshot.iterations,
+                    best.action.col + 1
+                ))),
+                Some(best) => lines.push(Line::from(format!(
+                    "  {} iterations, best {}, {:.1}% wins",
+                    snapshot.iterations,
+                    best.action.col + 1,
+                    best.win_rate * 100.0
+                ))),
+                None => lines.push(Line::from("  starting")),
+            }
+            if !snapshot.pv.is_empty() {
+                lines.push(Line::from(format!("  pv {}", pv_text(&snapshot.pv))));
+            }
+
+            let total = snapshot.stats.iter().map(|s| s.visits).sum::<u64>().max(1);
+            let mut stats = snapshot.stats.clone();
+            stats.sort_by_key(|s| s.action.col);
+            let visits = stats
+                .iter()
+                .map(|s| {
+                    let share = s.visits * 100 / total;
+                    format!("{}:{share}%", s.action.col + 1)
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            lines.push(Line::from(format!("  visits {visits}")).gray());
+        }
+
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watch(delay: Duration) -> Watch {
+        let ai = |seed| AiConfig {
+            budget: Budget::Iterations(200),
+            selection: MoveSelection::MostVisits,
+            threads: 1,
+            search: SearchConfig {
+                seed: Some(seed),
+                ..SearchConfig::default()
+            },
+        };
+        Watch::new(ai(1), ai(2), delay)
+    }
+
+    /// Polls for a second at most, until a move comes.
+    fn wait(watch: &mut Watch, game: &CFGameState) -> Option<CFMove> {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(1) {
+            if let Some(action) = watch.poll(game) {
+                return Some(action);
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        None
+    }
+
+    #[test]
+    fn plays_both_sides() {
+        let mut watch = watch(Duration::ZERO);
+        let mut game = CFGameState::default();
+        for player in [Position::Red, Position::Black, Position::Red] {
+            let action = wait(&mut watch, &game).unwrap();
+            assert_eq!(action.color, player);
+            assert_eq!(watch.iterations(player), 200);
+            assert_eq!(watch.searched().len(), 1);
+            game = game.apply_move(action);
+        }
+        assert!(!watch.analysis(&game, false).is_empty());
+    }
+
+    #[test]
+    fn pause_and_step() {
+        let mut watch = watch(Duration::ZERO);
+        let game = CFGameState::default();
+        watch.toggle_pause();
+        assert!(watch.is_paused());
+        assert_eq!(wait(&mut watch, &game), None);
+        // the search has finished, but waits for a step
+        assert_eq!(watch.iterations(Position::Red), 200);
+
+        watch.step();
+        let action = watch.poll(&game).unwrap();
+        let game = game.apply_move(action);
+        assert_eq!(wait(&mut watch, &game), None);
+
+        watch.toggle_pause();
+        assert!(!watch.is_paused());
+        assert!(wait(&mut watch, &game).is_some());
+    }
+
+    #[test]
+    fn waits_for_the_delay() {
+        let mut watch = watch(Duration::from_secs(60));
+        assert_eq!(wait(&mut watch, &CFGameState::default()), None);
+        watch.step();
+        assert!(watch.poll(&CFGameState::default()).is_some());
+    }
+}